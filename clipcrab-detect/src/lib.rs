@@ -4,14 +4,23 @@
 //! - Always load images as RGB 3-channel U8 Mats (yes, OpenCV typically does BGR, but we think that's lame and it makes OCR loads more annoying)
 //! 
 
+pub mod locale;
+pub mod match_name;
 pub mod matchers;
 pub mod utils;
 pub mod seasons;
 pub mod ocr;
 pub mod qr;
+pub mod roster;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, serde::Serialize, serde::Deserialize, Hash)]
+/// Serializes as the compact form, see [`MatchKey::compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, Hash)]
+#[serde(into = "String", try_from = "String")]
 pub enum MatchKey {
+    Practice {
+        /// Match number
+        num: u64
+    },
     Qualification {
         /// Match number
         num: u64
@@ -22,15 +31,115 @@ pub enum MatchKey {
         /// Tiebreaker count. Tiebreaker matches are those with a count greater than 1.
         tiebreaker: u64,
     },
+    /// Pre-double-elimination semifinal, e.g. "Semifinal 1-2"
+    Semifinal {
+        /// Semifinal series (1 or 2)
+        series: u64,
+        /// Match number within the series
+        num: u64,
+    },
+    /// Pre-double-elimination final, e.g. "Final 2"
+    Final {
+        /// Match number
+        num: u64,
+    },
+}
+
+impl MatchKey {
+    /// Sorts practice, then qualifications, then the playoffs.
+    fn sort_key(&self) -> (u8, u64, u64) {
+        match *self {
+            MatchKey::Practice { num } => (0, num, 0),
+            MatchKey::Qualification { num } => (1, num, 0),
+            MatchKey::Semifinal { series, num } => (2, series, num),
+            MatchKey::Final { num } => (3, num, 0),
+            MatchKey::Playoff { num, tiebreaker } => (4, num, tiebreaker),
+        }
+    }
+
+    /// Short canonical form for file names and command lines: `PR1`, `Q12`, `P3`, `P3-T2`, `SF1-2`, `F2`.
+    ///
+    /// Tiebreakers use the raw tiebreaker count, so `P3-T2` is the first tiebreaker of playoff match 3.
+    pub fn compact(&self) -> String {
+        match *self {
+            MatchKey::Practice { num } => format!("PR{num}"),
+            MatchKey::Qualification { num } => format!("Q{num}"),
+            MatchKey::Playoff { num, tiebreaker: 1 } => format!("P{num}"),
+            MatchKey::Playoff { num, tiebreaker } => format!("P{num}-T{tiebreaker}"),
+            MatchKey::Semifinal { series, num } => format!("SF{series}-{num}"),
+            MatchKey::Final { num } => format!("F{num}"),
+        }
+    }
+
+    /// Parses the compact form from [`MatchKey::compact`]. Case-insensitive.
+    ///
+    /// Only takes the canonical form: `P3-T1` is written `P3`, and there's no `P3-T0`.
+    pub fn from_compact(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim().to_uppercase();
+        let split = s.find(|c: char| c.is_ascii_digit()).ok_or(anyhow::anyhow!("No match number in `{s}`"))?;
+        let (prefix, rest) = s.split_at(split);
+        Ok(match (prefix, rest.split_once('-')) {
+            ("PR", None) => Self::Practice { num: rest.parse()? },
+            ("Q", None) => Self::Qualification { num: rest.parse()? },
+            ("P", None) => Self::Playoff { num: rest.parse()?, tiebreaker: 1 },
+            ("P", Some((num, tiebreaker))) => {
+                let tiebreaker = tiebreaker.strip_prefix('T').ok_or(anyhow::anyhow!("Bad tiebreaker in `{s}`"))?.parse()?;
+                if tiebreaker < 2 {
+                    anyhow::bail!("Tiebreakers start at T2, got `{s}`");
+                }
+                Self::Playoff { num: num.parse()?, tiebreaker }
+            }
+            ("SF", Some((series, num))) => Self::Semifinal { series: series.parse()?, num: num.parse()? },
+            ("F", None) => Self::Final { num: rest.parse()? },
+            _ => anyhow::bail!("Unknown compact match key `{s}`"),
+        })
+    }
+}
+
+impl From<MatchKey> for String {
+    fn from(value: MatchKey) -> Self {
+        value.compact()
+    }
+}
+
+impl TryFrom<String> for MatchKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        MatchKey::from_compact(&value)
+    }
 }
 
 impl core::str::FromStr for MatchKey {
     type Err = anyhow::Error;
 
+    /// Parses either the compact form (`Q12`, see [`MatchKey::from_compact`])
+    /// or the display form (`Qualification 12`, see [`MatchKey::from_display`]).
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_compact(s).or_else(|_| Self::from_display(s))
+    }
+}
+
+impl MatchKey {
+    /// Parses the display form (`Qualification 12`), and the other names scoring software has used.
+    ///
+    /// Unlike [`str::parse`], this doesn't take the compact form, which is what we want for OCR'd names:
+    /// a stray `Q1` on screen isn't a match name.
+    pub fn from_display(s: &str) -> anyhow::Result<Self> {
         let s_lower = s.trim().to_lowercase();
         let parts = s_lower.split_whitespace().collect::<Vec<&str>>();
         match parts[..] {
+            ["practice", "match", n, ..] | ["practice", n, ..] => {
+                Ok(Self::Practice { num: n.parse()? })
+            }
+            // only "Semifinal 1-2" and "Final 2"; "Final Match 3" is a playoff match, see below
+            ["semifinal", series_num, ..] if is_series_num(series_num) => {
+                let (series, n) = series_num.split_once('-').unwrap();
+                Ok(Self::Semifinal { series: series.parse()?, num: n.parse()? })
+            }
+            ["final", n, ..] if n.parse::<u64>().is_ok() => {
+                Ok(Self::Final { num: n.parse()? })
+            }
             ["qualification", n, ..] => {
                 Ok(Self::Qualification { num: n.parse()? })
             }
@@ -52,26 +161,29 @@ impl core::str::FromStr for MatchKey {
     }
 }
 
+/// Whether a token looks like the `1-2` in "Semifinal 1-2".
+fn is_series_num(token: &str) -> bool {
+    token.split_once('-').is_some_and(|(series, n)| series.parse::<u64>().is_ok() && n.parse::<u64>().is_ok())
+}
+
 impl Ord for MatchKey {
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
-        match (self, other) {
-            (MatchKey::Qualification { num }, MatchKey::Qualification { num: other_num }) => num.cmp(other_num),
-            (MatchKey::Qualification { .. }, MatchKey::Playoff { .. }) => core::cmp::Ordering::Less,
-            (MatchKey::Playoff { .. }, MatchKey::Qualification { .. }) => core::cmp::Ordering::Greater,
-            (MatchKey::Playoff { num, tiebreaker }, MatchKey::Playoff { num: other_num, tiebreaker: other_tiebreaker }) => {
-                if num == other_num {
-                    tiebreaker.cmp(other_tiebreaker)
-                } else {
-                    num.cmp(other_num)
-                }
-            }
-        }
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for MatchKey {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 impl core::fmt::Display for MatchKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            MatchKey::Practice { num } => write!(f, "Practice Match {num}"),
+            MatchKey::Semifinal { series, num } => write!(f, "Semifinal {series}-{num}"),
+            MatchKey::Final { num } => write!(f, "Final {num}"),
             MatchKey::Qualification { num } => write!(f, "Qualification {num}"),
             MatchKey::Playoff { num, tiebreaker: 1 } => write!(f, "Playoff Match {num}"),
             MatchKey::Playoff { num, tiebreaker: 2 } => write!(f, "Playoff Match {num} Tiebreaker"),
@@ -83,6 +195,8 @@ impl core::fmt::Display for MatchKey {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum MatchPhase {
+    /// Match preview (team intro). There's no clock on screen, so the time is always the full 2:30.
+    Preview,
     /// Match not started yet.
     NotStarted,
     /// Autonomous period
@@ -95,14 +209,30 @@ pub enum MatchPhase {
     Ended,
 }
 
+/// Which scoring display layout is on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DisplayLayout {
+    /// Red and blue alliances, one on each side
+    #[default]
+    Traditional,
+    /// Remote events: one team playing on its own, nobody across from it
+    Remote,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
 pub struct MatchDisplayInfo {
-    /// Red alliance teams
+    /// Scoring display layout
+    #[serde(default)]
+    pub layout: DisplayLayout,
+    /// Red alliance teams, however many the display lists. On remote events, the one team playing.
     pub red_alliance: Vec<u64>,
-    /// Blue alliance teams
+    /// Blue alliance teams. Empty on remote events.
     pub blue_alliance: Vec<u64>,
     /// Whether the display is flipped
     pub display_flipped: bool,
+    /// Team numbers that were read but couldn't be matched to the roster
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unresolved_teams: Vec<u64>,
 }
 
 /// Final match result struct
@@ -110,6 +240,9 @@ pub struct MatchDisplayInfo {
 pub struct MatchDetection {
     /// match name, e.g. Qualification X of Y
     pub name: String,
+    /// event name, if it could be read
+    #[serde(default)]
+    pub event_name: Option<String>,
     /// match time
     pub time: i64,
     /// match phase
@@ -118,6 +251,188 @@ pub struct MatchDetection {
     pub display_info: MatchDisplayInfo,
 }
 
+/// A results screen, identified either from its QR code or from its title.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ResultsScreen {
+    pub key: MatchKey,
+    /// ftc.events event code. Only QR codes tell us this.
+    #[serde(default)]
+    pub event_code: Option<String>,
+    /// Season year, if the QR code had one
+    #[serde(default)]
+    pub season: Option<u32>,
+    /// Final scores, if they could be read
+    #[serde(default)]
+    pub scores: Option<ResultsScores>,
+}
+
+/// Final scores for both alliances as shown on the results screen.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ResultsScores {
+    pub red: AllianceScore,
+    pub blue: AllianceScore,
+}
+
+/// One alliance's final score and its breakdown. Breakdown fields are `None` if they couldn't be read.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AllianceScore {
+    pub total: u64,
+    pub auto: Option<u64>,
+    pub teleop: Option<u64>,
+    /// Points awarded from the other alliance's fouls
+    pub penalty: Option<u64>,
+}
+
+impl From<qr::FTCEventsQR> for ResultsScreen {
+    fn from(qr: qr::FTCEventsQR) -> Self {
+        Self { key: qr.key, event_code: Some(qr.event_code), season: qr.season, scores: None }
+    }
+}
+
+/// What a frame shows.
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum FrameClass {
+    /// Scoring display, before, during, or right after a match
+    InMatch(MatchDetection),
+    /// Match preview with the teams, before the scoring display comes up. Has [`MatchPhase::Preview`].
+    Preview(MatchDetection),
+    /// Full-screen results
+    Results(ResultsScreen),
+    AllianceSelection,
+    /// Awards ceremony
+    Awards,
+    /// Blank or near-blank screen, e.g. between matches
+    Idle,
+    /// Nothing we recognize
+    Unknown,
+}
+
 pub trait Detector {
-    fn detect(&self, frame: &opencv::core::Mat) -> Option<MatchDetection>;
-}
\ No newline at end of file
+    fn detect(&mut self, frame: &opencv::core::Mat) -> FrameClass;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_naming_schemes() {
+        let cases = [
+            ("Practice Match 1", MatchKey::Practice { num: 1 }),
+            ("Practice 1", MatchKey::Practice { num: 1 }),
+            ("Qualification 12", MatchKey::Qualification { num: 12 }),
+            ("Qualification 12 of 40", MatchKey::Qualification { num: 12 }),
+            ("Semifinal 1-2", MatchKey::Semifinal { series: 1, num: 2 }),
+            ("Semifinal 2-3", MatchKey::Semifinal { series: 2, num: 3 }),
+            ("Final 2", MatchKey::Final { num: 2 }),
+            // DECODE
+            ("Match 3", MatchKey::Playoff { num: 3, tiebreaker: 1 }),
+            // INTO THE DEEP
+            ("Playoff Match 3", MatchKey::Playoff { num: 3, tiebreaker: 1 }),
+            ("Playoff Match 3 Tiebreaker", MatchKey::Playoff { num: 3, tiebreaker: 2 }),
+            ("Playoff Match 3 Tiebreaker 2", MatchKey::Playoff { num: 3, tiebreaker: 3 }),
+            // double elimination brackets that name their last rounds
+            ("Final Match 3", MatchKey::Playoff { num: 3, tiebreaker: 1 }),
+            ("Semifinal Match 2", MatchKey::Playoff { num: 2, tiebreaker: 1 }),
+        ];
+        for (name, key) in cases {
+            assert_eq!(name.parse::<MatchKey>().unwrap(), key, "{name}");
+        }
+    }
+
+    #[test]
+    fn from_str_rejects() {
+        for name in ["", "Intermission", "Semifinal", "Semifinal 1", "Final", "Qualification twelve"] {
+            assert!(name.parse::<MatchKey>().is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let keys = [
+            MatchKey::Practice { num: 1 },
+            MatchKey::Qualification { num: 12 },
+            MatchKey::Semifinal { series: 1, num: 2 },
+            MatchKey::Final { num: 2 },
+            MatchKey::Playoff { num: 3, tiebreaker: 1 },
+            MatchKey::Playoff { num: 3, tiebreaker: 2 },
+            MatchKey::Playoff { num: 3, tiebreaker: 3 },
+        ];
+        for key in keys {
+            assert_eq!(key.to_string().parse::<MatchKey>().unwrap(), key, "{key}");
+        }
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let cases = [
+            (MatchKey::Practice { num: 1 }, "PR1"),
+            (MatchKey::Qualification { num: 12 }, "Q12"),
+            (MatchKey::Playoff { num: 3, tiebreaker: 1 }, "P3"),
+            (MatchKey::Playoff { num: 3, tiebreaker: 2 }, "P3-T2"),
+            (MatchKey::Playoff { num: 3, tiebreaker: 3 }, "P3-T3"),
+            (MatchKey::Semifinal { series: 1, num: 2 }, "SF1-2"),
+            (MatchKey::Final { num: 2 }, "F2"),
+        ];
+        for (key, compact) in cases {
+            assert_eq!(key.compact(), compact);
+            assert_eq!(MatchKey::from_compact(compact).unwrap(), key, "{compact}");
+            assert_eq!(MatchKey::from_compact(&compact.to_lowercase()).unwrap(), key, "{compact}");
+            // serde goes through the compact form too
+            let json = serde_json::to_string(&key).unwrap();
+            assert_eq!(json, format!("\"{compact}\""));
+            assert_eq!(serde_json::from_str::<MatchKey>(&json).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn compact_rejects() {
+        for compact in ["", "Q", "P3-T0", "P3-T1", "P3-2", "SF1", "X12", "Q12-3", "Qualification 12"] {
+            assert!(MatchKey::from_compact(compact).is_err(), "{compact}");
+        }
+    }
+
+    #[test]
+    fn from_str_takes_both_forms() {
+        let cases = [
+            ("Q12", MatchKey::Qualification { num: 12 }),
+            ("Qualification 12", MatchKey::Qualification { num: 12 }),
+            ("p3-t2", MatchKey::Playoff { num: 3, tiebreaker: 2 }),
+            ("Playoff Match 3 Tiebreaker", MatchKey::Playoff { num: 3, tiebreaker: 2 }),
+            ("PR1", MatchKey::Practice { num: 1 }),
+            ("Practice 1", MatchKey::Practice { num: 1 }),
+            ("F2", MatchKey::Final { num: 2 }),
+            ("Final 2", MatchKey::Final { num: 2 }),
+        ];
+        for (s, key) in cases {
+            assert_eq!(s.parse::<MatchKey>().unwrap(), key, "{s}");
+            // and both of the key's own forms come back to it
+            assert_eq!(key.compact().parse::<MatchKey>().unwrap(), key, "{s}");
+            assert_eq!(key.to_string().parse::<MatchKey>().unwrap(), key, "{s}");
+        }
+        for compact in ["Q1", "F2", "P3", "PR1"] {
+            assert!(MatchKey::from_display(compact).is_err(), "{compact}");
+        }
+    }
+
+    #[test]
+    fn ord_follows_the_event() {
+        let mut keys = vec![
+            MatchKey::Playoff { num: 1, tiebreaker: 1 },
+            MatchKey::Final { num: 1 },
+            MatchKey::Qualification { num: 2 },
+            MatchKey::Semifinal { series: 1, num: 1 },
+            MatchKey::Practice { num: 1 },
+            MatchKey::Qualification { num: 1 },
+        ];
+        keys.sort();
+        assert_eq!(keys, vec![
+            MatchKey::Practice { num: 1 },
+            MatchKey::Qualification { num: 1 },
+            MatchKey::Qualification { num: 2 },
+            MatchKey::Semifinal { series: 1, num: 1 },
+            MatchKey::Final { num: 1 },
+            MatchKey::Playoff { num: 1, tiebreaker: 1 },
+        ]);
+    }
+}
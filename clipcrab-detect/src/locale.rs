@@ -0,0 +1,270 @@
+//! Localized scoring software.
+//!
+//! Events running the scoring system in another language show `Clasificación 12` instead of `Qualification 12`.
+//! Each locale has a table from its match name and screen title words to the English ones, which is what the rest of the parsing knows,
+//! and the extra characters the OCR needs to be allowed to read them.
+
+/// Characters every locale gets: ASCII letters, digits, and a space.
+const BASE_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Locale {
+    #[default]
+    English,
+    Spanish,
+    Portuguese,
+    Romanian,
+    Chinese,
+}
+
+impl Locale {
+    /// Localized word -> English word, lowercase.
+    ///
+    /// Entries with spaces are phrases, for names whose word order doesn't carry over word by word.
+    fn vocabulary(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::English => &[],
+            Locale::Spanish => &[
+                ("partido de eliminatoria", "playoff match"),
+                ("práctica", "practice"),
+                ("practica", "practice"),
+                ("clasificación", "qualification"),
+                ("clasificacion", "qualification"),
+                ("calificación", "qualification"),
+                ("calificacion", "qualification"),
+                ("eliminatoria", "playoff"),
+                ("semifinal", "semifinal"),
+                ("final", "final"),
+                ("partido", "match"),
+                ("encuentro", "match"),
+                ("desempate", "tiebreaker"),
+                ("de", "of"),
+                ("resultados", "results"),
+                ("alianzas", "alliance"),
+                ("selección", "selection"),
+                ("seleccion", "selection"),
+                ("premios", "awards"),
+                ("premio", "award"),
+            ],
+            Locale::Portuguese => &[
+                ("partida de eliminatória", "playoff match"),
+                ("partida de eliminatoria", "playoff match"),
+                ("prática", "practice"),
+                ("pratica", "practice"),
+                ("treino", "practice"),
+                ("classificação", "qualification"),
+                ("classificacao", "qualification"),
+                ("qualificação", "qualification"),
+                ("qualificacao", "qualification"),
+                ("eliminatória", "playoff"),
+                ("eliminatoria", "playoff"),
+                ("semifinal", "semifinal"),
+                ("final", "final"),
+                ("partida", "match"),
+                ("desempate", "tiebreaker"),
+                ("de", "of"),
+                ("resultados", "results"),
+                ("alianças", "alliance"),
+                ("aliancas", "alliance"),
+                ("seleção", "selection"),
+                ("selecao", "selection"),
+                ("prêmios", "awards"),
+                ("premios", "awards"),
+                ("prêmio", "award"),
+                ("premio", "award"),
+            ],
+            Locale::Romanian => &[
+                ("antrenament", "practice"),
+                ("calificare", "qualification"),
+                ("calificări", "qualification"),
+                ("calificari", "qualification"),
+                ("eliminatorie", "playoff"),
+                ("eliminatoriu", "playoff"),
+                ("semifinală", "semifinal"),
+                ("semifinala", "semifinal"),
+                ("finală", "final"),
+                ("finala", "final"),
+                ("meci", "match"),
+                ("meciul", "match"),
+                ("departajare", "tiebreaker"),
+                ("din", "of"),
+                ("rezultate", "results"),
+                ("rezultatele", "results"),
+                ("alianțelor", "alliance"),
+                ("aliantelor", "alliance"),
+                ("selecția", "selection"),
+                ("selectia", "selection"),
+                ("premii", "awards"),
+                ("premiul", "award"),
+            ],
+            Locale::Chinese => &[
+                ("练习赛", "practice"),
+                ("练习", "practice"),
+                ("资格赛", "qualification"),
+                ("淘汰赛", "playoff"),
+                ("半决赛", "semifinal"),
+                ("决赛", "final"),
+                ("比赛", "match"),
+                ("加赛", "tiebreaker"),
+                ("结果", "results"),
+                ("联盟", "alliance"),
+                ("选择", "selection"),
+                ("颁奖", "awards"),
+                ("奖", "award"),
+            ],
+        }
+    }
+
+    /// Characters the match name OCR is allowed to produce.
+    pub fn ocr_chars(&self) -> String {
+        let mut chars = BASE_CHARS.to_string();
+        for (word, _) in self.vocabulary() {
+            for c in word.chars() {
+                if !chars.contains(c) {
+                    chars.push(c);
+                }
+                for upper in c.to_uppercase() {
+                    if !chars.contains(upper) {
+                        chars.push(upper);
+                    }
+                }
+            }
+        }
+        chars
+    }
+
+    /// Swaps localized words and phrases for their English ones, leaving everything else alone.
+    ///
+    /// Words OCR dropped an accent from or misread by a letter still count, as long as they're long enough to be sure.
+    pub fn translate(&self, text: &str) -> String {
+        let vocabulary = self.vocabulary();
+        if vocabulary.is_empty() {
+            return text.to_string();
+        }
+        let tokens = split_scripts(text);
+        let lower = tokens.iter().map(|token| token.to_lowercase()).collect::<Vec<String>>();
+        let fuzzy = |token: &str, word: &str| token.chars().count() >= 5 && crate::utils::edit_distance(token, word) <= 1;
+        // how many tokens from `at` on make up `entry`, if they do
+        let matches = |at: usize, entry: &str, exact: bool| {
+            let words = entry.split(' ').collect::<Vec<&str>>();
+            let window = lower.get(at..at + words.len())?;
+            window
+                .iter()
+                .zip(&words)
+                .all(|(token, word)| token == word || (!exact && fuzzy(token, word)))
+                .then_some(words.len())
+        };
+
+        let mut translated = Vec::new();
+        let mut at = 0;
+        while at < tokens.len() {
+            // phrases first, then exact words, then words OCR got slightly wrong
+            let phrases = vocabulary.iter().filter(|(entry, _)| entry.contains(' '));
+            let found = phrases
+                .clone()
+                .find_map(|(entry, english)| Some((matches(at, entry, false)?, english)))
+                .or_else(|| vocabulary.iter().find_map(|(entry, english)| Some((matches(at, entry, true)?, english))))
+                .or_else(|| vocabulary.iter().find_map(|(entry, english)| Some((matches(at, entry, false)?, english))));
+            match found {
+                Some((len, english)) => {
+                    translated.push(english.to_string());
+                    at += len;
+                }
+                None => {
+                    translated.push(tokens[at].clone());
+                    at += 1;
+                }
+            }
+        }
+        translated.join(" ")
+    }
+}
+
+/// Splits on whitespace, and also between CJK characters and digits, since `资格赛12` often has no space.
+fn split_scripts(text: &str) -> Vec<String> {
+    let is_cjk = |c: char| c >= '\u{2E80}';
+    let mut tokens = Vec::new();
+    for word in text.split_whitespace() {
+        let mut current = String::new();
+        let mut prev_cjk = None;
+        for c in word.chars() {
+            let cjk = is_cjk(c);
+            if prev_cjk.is_some_and(|p| p != cjk) {
+                tokens.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+            prev_cjk = Some(cjk);
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatchKey;
+
+    #[test]
+    fn translate_each_locale() {
+        let playoff = |num, tiebreaker| MatchKey::Playoff { num, tiebreaker };
+        let cases = [
+            (Locale::English, "Qualification 12", MatchKey::Qualification { num: 12 }),
+            (Locale::Spanish, "Clasificación 12", MatchKey::Qualification { num: 12 }),
+            (Locale::Spanish, "Partido de Eliminatoria 3", playoff(3, 1)),
+            (Locale::Spanish, "Partido 3 Desempate", playoff(3, 2)),
+            (Locale::Portuguese, "Classificação 12", MatchKey::Qualification { num: 12 }),
+            (Locale::Portuguese, "Partida de Eliminatória 3", playoff(3, 1)),
+            (Locale::Portuguese, "Partida 3 Desempate", playoff(3, 2)),
+            (Locale::Romanian, "Calificare 12", MatchKey::Qualification { num: 12 }),
+            (Locale::Romanian, "Meci 3", playoff(3, 1)),
+            (Locale::Chinese, "资格赛12", MatchKey::Qualification { num: 12 }),
+            (Locale::Chinese, "比赛 3", playoff(3, 1)),
+        ];
+        for (locale, text, key) in cases {
+            let english = locale.translate(text);
+            let parsed = MatchKey::from_display(&english).unwrap_or_else(|e| panic!("{locale:?} {text} -> {english}: {e}"));
+            assert_eq!(parsed, key, "{locale:?} {text} -> {english}");
+        }
+    }
+
+    #[test]
+    fn translate_phrases() {
+        assert_eq!(Locale::Spanish.translate("Partido de Eliminatoria 3"), "playoff match 3");
+        // words of the phrase get the same OCR slack as words on their own
+        assert_eq!(Locale::Spanish.translate("Partido de Eliminatorla 3"), "playoff match 3");
+        // "de" on its own is still "of"
+        assert_eq!(Locale::Spanish.translate("Selección de Alianzas"), "selection of alliance");
+    }
+
+    #[test]
+    fn translate_tolerates_ocr() {
+        // dropped accent, one letter off
+        assert_eq!(Locale::Spanish.translate("Clasificacion 12"), "qualification 12");
+        assert_eq!(Locale::Spanish.translate("Clasificaclón 12"), "qualification 12");
+        // short words have to be exact
+        assert_eq!(Locale::Spanish.translate("Fina 2"), "Fina 2");
+    }
+
+    #[test]
+    fn ocr_chars_cover_vocabulary() {
+        assert!(Locale::English.ocr_chars().is_ascii());
+        for locale in [Locale::Spanish, Locale::Portuguese, Locale::Romanian, Locale::Chinese] {
+            let chars = locale.ocr_chars();
+            assert!(chars.starts_with(BASE_CHARS), "{locale:?}");
+            for (word, _) in locale.vocabulary() {
+                assert!(word.chars().all(|c| chars.contains(c)), "{locale:?} {word}");
+            }
+        }
+        assert!(Locale::Spanish.ocr_chars().contains('Ó'));
+    }
+
+    #[test]
+    fn split_scripts_cjk_and_digits() {
+        assert_eq!(split_scripts("资格赛12"), vec!["资格赛", "12"]);
+        assert_eq!(split_scripts("第 3 场"), vec!["第", "3", "场"]);
+        assert_eq!(split_scripts("Match 3"), vec!["Match", "3"]);
+    }
+}
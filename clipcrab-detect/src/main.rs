@@ -1,4 +1,4 @@
-use std::{io::Write as _, time::Instant};
+use std::{io::Write as _, path::PathBuf, time::Instant};
 
 use clap::Parser;
 
@@ -6,8 +6,12 @@ use clap::Parser;
 enum Detector {
     /// match-result-qr
     MatchResultQR,
+    /// season2025-decode-results
+    Season2025DecodeResults,
     /// seasson2025-decode
     Season2025Decode,
+    /// season2025-decode-frame, everything the clipper looks for
+    Season2025DecodeFrame,
 }
 #[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
 enum FileInput {
@@ -23,6 +27,12 @@ enum FileInput {
 #[derive(clap::Parser)]
 struct Cli {
     detector: Detector,
+    /// Event roster to check team numbers against
+    #[arg(short, long)]
+    roster: Option<PathBuf>,
+    /// Language of the scoring software
+    #[arg(long, value_enum, default_value_t)]
+    locale: clipcrab_detect::locale::Locale,
     #[command(subcommand)]
     input: FileInput,
 }
@@ -40,7 +50,7 @@ fn main() {
         }
         FileInput::Frame { fname, start } => {
             clipcrab_io::init().unwrap();
-            clipcrab_io::seek::FFMpegger::new(fname.as_ref()).unwrap().extract_mat(clipcrab_io::time::parse_time(&start).unwrap()).unwrap()
+            clipcrab_io::seek::FFMpegger::new(fname.as_ref()).unwrap().extract_mat(clipcrab_io::time::parse_time(&start).unwrap()).unwrap().1
         }
     };
 
@@ -50,15 +60,34 @@ fn main() {
 
     let detection = match cli.detector {
         Detector::MatchResultQR => {
-            let detection = clipcrab_detect::qr::detect_qr(&frame);
+            let mut qr = clipcrab_detect::qr::QrDetector::new();
+            let detection = qr.detect(&frame).or_else(|| qr.search(&frame));
     
             tracing::trace!("Process image total: {:.3} ms", (Instant::now() - start).as_secs_f64() * 1000.0);
             write!(std::io::stdout(), "{}", serde_json::to_string_pretty(&detection).unwrap()).unwrap();
             return;
         }
+        Detector::Season2025DecodeResults => {
+            let detection = clipcrab_detect::seasons::s2025_decode_results::DecodeResultsDetector::new()
+                .with_locale(cli.locale)
+                .detect(&frame);
+
+            tracing::trace!("Process image total: {:.3} ms", (Instant::now() - start).as_secs_f64() * 1000.0);
+            write!(std::io::stdout(), "{}", serde_json::to_string_pretty(&detection).unwrap()).unwrap();
+            return;
+        }
         Detector::Season2025Decode => {
-            let detector = clipcrab_detect::seasons::s2025_decode::DecodeDetector::new();
-            detector.detect(&frame)
+            decode_detector(cli.roster, cli.locale).detect(&frame)
+        }
+        Detector::Season2025DecodeFrame => {
+            use clipcrab_detect::Detector as _;
+            let detection = clipcrab_detect::seasons::s2025_decode::DecodeFrameDetector::new(decode_detector(cli.roster, cli.locale))
+                .with_locale(cli.locale)
+                .detect(&frame);
+
+            tracing::trace!("Process image total: {:.3} ms", (Instant::now() - start).as_secs_f64() * 1000.0);
+            write!(std::io::stdout(), "{}", serde_json::to_string_pretty(&detection).unwrap()).unwrap();
+            return;
         }
     };
 
@@ -66,3 +95,11 @@ fn main() {
 
     write!(std::io::stdout(), "{}", serde_json::to_string_pretty(&detection).unwrap()).unwrap();
 }
+
+fn decode_detector(roster: Option<PathBuf>, locale: clipcrab_detect::locale::Locale) -> clipcrab_detect::seasons::s2025_decode::DecodeDetector {
+    let detector = clipcrab_detect::seasons::s2025_decode::DecodeDetector::new().with_locale(locale);
+    match roster {
+        Some(roster) => detector.with_roster(clipcrab_detect::roster::Roster::load(&roster).unwrap()),
+        None => detector,
+    }
+}
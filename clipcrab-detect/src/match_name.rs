@@ -0,0 +1,245 @@
+//! Tolerant parsing of OCR'd match names.
+//!
+//! OCR likes to hand us things like `Qualificati0n 12` or `PIayoff Match l2`, which `MatchKey::from_display` rightly refuses.
+//! Here we snap each word to the closest known one and fix up digit look-alikes in numbers,
+//! then hand the cleaned-up name to the strict parser.
+use crate::{MatchKey, locale::Locale, utils::edit_distance};
+
+/// Words that show up in match names.
+const VOCABULARY: &[&str] = &["practice", "qualification", "playoff", "semifinal", "final", "match", "tiebreaker", "of"];
+
+/// Truncated words need at least this many characters left to count.
+const MIN_TRUNCATED_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParsedMatchName {
+    pub key: MatchKey,
+    /// 1.0 for an exact parse, lower the more we had to fix up
+    pub confidence: f64,
+}
+
+/// Parses a match name from localized scoring software, see [`Locale`].
+pub fn parse_localized(name: &str, locale: Locale) -> anyhow::Result<ParsedMatchName> {
+    parse(&locale.translate(name))
+}
+
+/// Parses a match name, tolerating common OCR mistakes.
+pub fn parse(name: &str) -> anyhow::Result<ParsedMatchName> {
+    if let Ok(key) = MatchKey::from_display(name) {
+        return Ok(ParsedMatchName { key, confidence: 1.0 });
+    }
+
+    let mut edits = 0_usize;
+    let mut chars = 0_usize;
+    let mut cleaned = Vec::new();
+    for token in name.split_whitespace() {
+        chars += token.chars().count();
+        if let Some((number, fixes)) = fix_number(token) {
+            edits += fixes;
+            cleaned.push(number);
+        } else if let Some((word, distance)) = fix_word(token) {
+            edits += distance;
+            cleaned.push(word.to_string());
+        } else {
+            cleaned.push(token.to_lowercase());
+        }
+    }
+
+    let cleaned = cleaned.join(" ");
+    let key = MatchKey::from_display(&cleaned)
+        .map_err(|e| anyhow::anyhow!("Could not parse `{name}` (cleaned up to `{cleaned}`): {e}"))?;
+    let confidence = if chars == 0 { 0.0 } else { 1.0 - edits as f64 / chars as f64 };
+    Ok(ParsedMatchName { key, confidence: confidence.max(0.0) })
+}
+
+/// Pulls a division or field out of an event name.
+///
+/// `FIRST Championship - Edison Division` gives `Edison`, `Some Event Field 2` gives `Field 2`.
+pub fn division(event_name: &str) -> Option<String> {
+    let words = event_name.split_whitespace().collect::<Vec<&str>>();
+    let lower = words.iter().map(|w| w.to_lowercase()).collect::<Vec<String>>();
+    if let Some(i) = lower.iter().position(|w| w == "division") {
+        // either "Edison Division" or "Division Edison"
+        return match i.checked_sub(1).map(|j| words[j]) {
+            Some(prev) if prev.chars().any(char::is_alphanumeric) => Some(prev.to_string()),
+            _ => words.get(i + 1).map(|w| w.to_string()),
+        };
+    }
+    let i = lower.iter().position(|w| w == "field")?;
+    words.get(i + 1).map(|n| format!("Field {n}"))
+}
+
+/// Reads a token as a number if it's mostly digits, mapping letters that look like digits.
+/// Returns the number and how many characters were swapped.
+fn fix_number(token: &str) -> Option<(String, usize)> {
+    let digits = token.chars().filter(char::is_ascii_digit).count();
+    if digits * 2 < token.chars().count() {
+        return None;
+    }
+    let mut fixes = 0;
+    let number = token
+        .chars()
+        .map(|c| {
+            let fixed = match c {
+                '0'..='9' | '-' => return Some(c),
+                'O' | 'o' | 'D' | 'Q' => '0',
+                'I' | 'l' | 'i' | '|' | '!' => '1',
+                'Z' | 'z' => '2',
+                'S' | 's' => '5',
+                'G' => '6',
+                'B' => '8',
+                _ => return None,
+            };
+            fixes += 1;
+            Some(fixed)
+        })
+        .collect::<Option<String>>()?;
+    Some((number, fixes))
+}
+
+/// Snaps a token to the closest vocabulary word, allowing for truncation.
+/// Returns the word and its edit distance.
+fn fix_word(token: &str) -> Option<(&'static str, usize)> {
+    let token = token.to_lowercase();
+    let len = token.chars().count();
+    VOCABULARY
+        .iter()
+        .filter_map(|word| {
+            let word_len = word.chars().count();
+            let distance = if len >= MIN_TRUNCATED_LEN && len < word_len {
+                // compare against the start of the word, and charge one edit for the truncation
+                let prefix = word.chars().take(len).collect::<String>();
+                edit_distance(&token, &prefix) + 1
+            } else {
+                edit_distance(&token, word)
+            };
+            (distance <= (word_len / 4).max(1)).then_some((*word, distance))
+        })
+        .min_by_key(|(_, distance)| *distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qual(num: u64) -> MatchKey {
+        MatchKey::Qualification { num }
+    }
+
+    fn playoff(num: u64, tiebreaker: u64) -> MatchKey {
+        MatchKey::Playoff { num, tiebreaker }
+    }
+
+    #[test]
+    fn parse_ocr_names() {
+        let cases = [
+            ("Qualification 12", qual(12)),
+            ("Qualificati0n 12", qual(12)),
+            ("QUALIFICATION 12", qual(12)),
+            ("Qualification l2", qual(12)),
+            ("Quallfication 1O", qual(10)),
+            ("PIayoff Match l2", playoff(12, 1)),
+            ("Match 3", playoff(3, 1)),
+            ("Playoff Match 3 Tiebreaker", playoff(3, 2)),
+            ("Playoff Match 3 Tiebreaker 2", playoff(3, 3)),
+            ("Practlce 4", MatchKey::Practice { num: 4 }),
+        ];
+        for (name, key) in cases {
+            let parsed = parse(name).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(parsed.key, key, "{name}");
+        }
+    }
+
+    #[test]
+    fn parse_truncated_words() {
+        let cases = [
+            ("Qualificat 12", qual(12)),
+            ("Qual 12", qual(12)),
+            ("Playoff Matc 5", playoff(5, 1)),
+            ("Playoff Match 5 Tiebrea", playoff(5, 2)),
+        ];
+        for (name, key) in cases {
+            let parsed = parse(name).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(parsed.key, key, "{name}");
+            assert!(parsed.confidence < 1.0, "{name}");
+        }
+    }
+
+    #[test]
+    fn parse_confidence() {
+        assert_eq!(parse("Qualification 12").unwrap().confidence, 1.0);
+        let exact = parse("Playoff Match 12").unwrap().confidence;
+        let one_fix = parse("Playoff Match l2").unwrap().confidence;
+        let two_fixes = parse("PIayoff Match l2").unwrap().confidence;
+        assert!(exact > one_fix && one_fix > two_fixes);
+        // still above the project's cutoff (`MIN_MATCH_NAME_CONFIDENCE`, 0.75)
+        assert!(two_fixes >= 0.75);
+    }
+
+    #[test]
+    fn parse_low_confidence() {
+        // parses, but with so much fixed up that the project throws it away (`MIN_MATCH_NAME_CONFIDENCE`, 0.75)
+        for name in ["0ual l2", "Qvalif l2"] {
+            let parsed = parse(name).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert!(parsed.confidence < 0.75, "{name}: {}", parsed.confidence);
+        }
+    }
+
+    #[test]
+    fn parse_rejects() {
+        // compact keys are OCR fragments here, not names we should trust
+        for name in ["", "Intermission", "Qualification", "Qualification twelve", "Awards Ceremony", "Q1", "F2", "P3"] {
+            assert!(parse(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn fix_number_lookalikes() {
+        assert_eq!(fix_number("12"), Some(("12".to_string(), 0)));
+        assert_eq!(fix_number("l2"), Some(("12".to_string(), 1)));
+        assert_eq!(fix_number("1O"), Some(("10".to_string(), 1)));
+        assert_eq!(fix_number("S8"), Some(("58".to_string(), 1)));
+        assert_eq!(fix_number("1-2"), Some(("1-2".to_string(), 0)));
+        assert_eq!(fix_number("Match"), None);
+        assert_eq!(fix_number("1x"), None);
+    }
+
+    #[test]
+    fn fix_word_snaps() {
+        assert_eq!(fix_word("Matcb"), Some(("match", 1)));
+        assert_eq!(fix_word("playoff"), Some(("playoff", 0)));
+        assert_eq!(fix_word("Qual"), Some(("qualification", 1)));
+        assert_eq!(fix_word("xyz"), None);
+        // too short to be a truncation
+        assert_eq!(fix_word("qu"), None);
+    }
+
+    #[test]
+    fn parse_localized_names() {
+        let cases = [
+            (Locale::English, "Qualification 12", qual(12)),
+            (Locale::Spanish, "Clasificación 12", qual(12)),
+            (Locale::Spanish, "Práctica 2", MatchKey::Practice { num: 2 }),
+            (Locale::Portuguese, "Classificação 12", qual(12)),
+            (Locale::Portuguese, "Partida 3", playoff(3, 1)),
+            (Locale::Romanian, "Calificare 12", qual(12)),
+            (Locale::Romanian, "Meci 3", playoff(3, 1)),
+            (Locale::Chinese, "资格赛12", qual(12)),
+            (Locale::Chinese, "比赛 3", playoff(3, 1)),
+        ];
+        for (locale, name, key) in cases {
+            let parsed = parse_localized(name, locale).unwrap_or_else(|e| panic!("{locale:?} {name}: {e}"));
+            assert_eq!(parsed.key, key, "{locale:?} {name}");
+        }
+        // English left in a localized name still parses
+        assert_eq!(parse_localized("Qualification 12", Locale::Spanish).unwrap().key, qual(12));
+    }
+
+    #[test]
+    fn division_names() {
+        assert_eq!(division("FIRST Championship - Edison Division").as_deref(), Some("Edison"));
+        assert_eq!(division("Division Franklin").as_deref(), Some("Franklin"));
+        assert_eq!(division("Some League Meet Field 2").as_deref(), Some("Field 2"));
+        assert_eq!(division("Some League Meet"), None);
+    }
+}
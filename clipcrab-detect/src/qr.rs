@@ -2,14 +2,148 @@ use opencv::{core::{Mat, MatTraitConst}, imgproc};
 
 use crate::{MatchKey, utils::{self, Point, Size}};
 
+/// Where the QR code sits on a full-screen results screen.
+const DEFAULT_LOCATION: QrLocation = QrLocation {
+    rel_x: 724. / 1920.,
+    rel_y: 788. / 1080.,
+    rel_width: 155. / 1920.,
+    rel_height: 155. / 1080.,
+};
+
+/// Frames get downscaled to at most this width for the full-frame search.
+const SEARCH_WIDTH: f64 = 1280.0;
+
+/// Where a QR code was found, in relative (0.0..1.0) frame coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QrLocation {
+    pub rel_x: f64,
+    pub rel_y: f64,
+    pub rel_width: f64,
+    pub rel_height: f64,
+}
+
+impl QrLocation {
+    /// Bounding box of rqrr grid corners in an image of the given size.
+    fn from_bounds(bounds: &[rqrr::Point; 4], size: opencv::core::Size) -> Self {
+        let (w, h) = (size.width as f64, size.height as f64);
+        let min_x = bounds.iter().map(|p| p.x).min().unwrap() as f64;
+        let max_x = bounds.iter().map(|p| p.x).max().unwrap() as f64;
+        let min_y = bounds.iter().map(|p| p.y).min().unwrap() as f64;
+        let max_y = bounds.iter().map(|p| p.y).max().unwrap() as f64;
+        Self {
+            rel_x: min_x / w,
+            rel_y: min_y / h,
+            rel_width: (max_x - min_x) / w,
+            rel_height: (max_y - min_y) / h,
+        }
+    }
+
+    /// Grows the box by `frac` of its size on every side, so we still get the quiet zone.
+    fn padded(&self, frac: f64) -> Self {
+        Self {
+            rel_x: self.rel_x - self.rel_width * frac,
+            rel_y: self.rel_y - self.rel_height * frac,
+            rel_width: self.rel_width * (1.0 + 2.0 * frac),
+            rel_height: self.rel_height * (1.0 + 2.0 * frac),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QrDetection {
+    pub qr: FTCEventsQR,
+    /// Where in the frame the code was found
+    pub location: QrLocation,
+}
+
+/// Finds results screen QR codes, remembering where the last one was.
+///
+/// [`QrDetector::detect`] only tries the last known spot and the usual full-screen spot, which is cheap enough for every frame.
+/// [`QrDetector::search`] looks over the whole frame, which is what saves us on streams that scale or offset the results screen,
+/// but it thresholds and scans the entire frame, so only call it on frames that look like a results screen or on a sample of the rest.
+#[derive(Debug, Default)]
+pub struct QrDetector {
+    last_location: Option<QrLocation>,
+}
+
+impl QrDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks where results screen QR codes have been found before.
+    pub fn detect(&mut self, mat: &Mat) -> Option<QrDetection> {
+        self.last_location
+            .into_iter()
+            .chain([DEFAULT_LOCATION])
+            .find_map(|location| decode_at(mat, location).map(|qr| QrDetection { qr, location }))
+    }
+
+    /// Searches the whole frame, remembering where the code was for [`QrDetector::detect`] on later frames.
+    pub fn search(&mut self, mat: &Mat) -> Option<QrDetection> {
+        let start = std::time::Instant::now();
+        let detection = search_qr(mat);
+        tracing::debug!("Full-frame QR search: {:.3} ms", start.elapsed().as_secs_f64() * 1000.0);
+
+        let detection = detection?;
+        tracing::debug!("Found QR code by full-frame search at {:?}", detection.location);
+        self.last_location = Some(detection.location);
+        Some(detection)
+    }
+}
+
+/// Checks the usual spot on a full-screen results screen.
 pub fn detect_qr(mat: &Mat) -> Option<FTCEventsQR> {
     // the coordinates assume that the qr code is in the same spot every time in a full-screen setting. 
-    // this is mostly a good assumption.
+    // this is mostly a good assumption. if it isn't, use [`QrDetector`].
+    decode_at(mat, DEFAULT_LOCATION)
+}
+
+/// Searches a whole (downscaled) frame for QR finder patterns.
+pub fn search_qr(mat: &Mat) -> Option<QrDetection> {
+    let size = mat.size().unwrap();
+    let scale = (SEARCH_WIDTH / size.width as f64).min(1.0);
+    let mut gray = utils::cvt_color(mat, imgproc::COLOR_RGB2GRAY);
+    if scale < 1.0 {
+        gray = utils::resize(&gray, scale, scale);
+    }
+    // the frame isn't evenly lit like a cropped QR code is, so the threshold has to be local
+    let mut bin = Mat::default();
+    imgproc::adaptive_threshold(
+        &gray,
+        &mut bin,
+        255.0,
+        imgproc::ADAPTIVE_THRESH_MEAN_C,
+        imgproc::THRESH_BINARY,
+        31,
+        10.0
+    ).unwrap();
+
+    let bin_size = bin.size().unwrap();
+    let mut img = rqrr::PreparedImage::prepare_from_bitmap(
+        bin_size.width as usize,
+        bin_size.height as usize,
+        |x, y| {
+            *bin.at_2d::<u8>(y as i32, x as i32).unwrap() == 0
+        }
+    );
+    for grid in img.detect_grids() {
+        let location = QrLocation::from_bounds(&grid.bounds, bin_size);
+        // the downscaled code may be too small to read, in which case we go back to the full-res frame
+        let qr = decode_grid(&grid).or_else(|| decode_at(mat, location.padded(0.2)));
+        if let Some(qr) = qr {
+            return Some(QrDetection { qr, location });
+        }
+    }
+    None
+}
+
+fn decode_at(mat: &Mat, location: QrLocation) -> Option<FTCEventsQR> {
     let roi = utils::relative_extract_roi(
         mat,
         mat.size().unwrap().into(),
-        Point::new(724./1920.,788./1080.),
-        Size::new(155./1920., 155./1080.)
+        Point::new(location.rel_x, location.rel_y),
+        Size::new(location.rel_width, location.rel_height)
     );
 
     let gray = utils::cvt_color(&roi, imgproc::COLOR_RGB2GRAY);
@@ -35,47 +169,81 @@ pub fn detect_qr(mat: &Mat) -> Option<FTCEventsQR> {
             *bin.at_2d::<u8>(y as i32, x as i32).unwrap() == 0
         }
     );
-    for grid in img.detect_grids() {
-        match grid.decode() {
-            Ok((_, value)) => {
-                tracing::trace!("Found QR code: {value}");
-                match FTCEventsQR::new(&value) {
-                    Ok(result) => return Some(result),
-                    Err(e) => {
-                        tracing::warn!("Could not parse QR code for URL {value}: {e}");
-                    }
-                }
+    img.detect_grids().iter().find_map(decode_grid)
+}
 
-            }
-            Err(e) => {
-                tracing::warn!("Found undecodable QR code: {e}");
+fn decode_grid<G: rqrr::BitGrid>(grid: &rqrr::Grid<G>) -> Option<FTCEventsQR> {
+    match grid.decode() {
+        Ok((_, value)) => {
+            tracing::trace!("Found QR code: {value}");
+            match FTCEventsQR::new(&value) {
+                Ok(result) => Some(result),
+                Err(e) => {
+                    tracing::warn!("Could not parse QR code for URL {value}: {e}");
+                    None
+                }
             }
         }
+        Err(e) => {
+            tracing::warn!("Found undecodable QR code: {e}");
+            None
+        }
     }
-    None
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct FTCEventsQR {
+    /// Season year, if the URL has one (e.g. `/2025/USCALAS/...`)
+    #[serde(default)]
+    pub season: Option<u32>,
     pub event_code: String,
     pub key: MatchKey,
 }
 
 impl FTCEventsQR {
+    /// Parses an ftc.events match URL.
+    ///
+    /// Handles http or https, an optional `www.`, an optional leading season year, and trailing slashes or query strings.
     pub fn new(url: &str) -> anyhow::Result<Self> {
-        let url = url::Url::parse(url)?;
+        let url = url::Url::parse(url.trim())?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!("Not a web URL: {url}");
+        }
         let host = url.host_str().ok_or(anyhow::anyhow!("No host string!"))?;
-        if host != "ftc.events" {
+        if host.trim_start_matches("www.") != "ftc.events" {
             anyhow::bail!("Not a valid host: {host}");
         }
-        let path = url.path_segments().ok_or(anyhow::anyhow!("No path segments!"))?.collect::<Vec<&str>>();
+        let mut path = url
+            .path_segments()
+            .ok_or(anyhow::anyhow!("No path segments!"))?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>();
+        let season = match path.first().map(|s| s.parse::<u32>()) {
+            Some(Ok(season)) if path.len() > 1 => {
+                path.remove(0);
+                Some(season)
+            }
+            _ => None,
+        };
         let (event_code, match_type) = match path[..] {
+            [event_code, "practice", num, ..] => {
+                (event_code.to_string(), MatchKey::Practice { num: num.parse()? })
+            }
+            [event_code, "semifinals", series, num, ..] => {
+                (event_code.to_string(), MatchKey::Semifinal { series: series.parse()?, num: num.parse()? })
+            }
+            [event_code, "finals", num, ..] => {
+                (event_code.to_string(), MatchKey::Final { num: num.parse()? })
+            }
             [event_code, "qualifications", num, ..] => {
                 (event_code.to_string(), MatchKey::Qualification { num: num.parse()? })
             }
             [event_code, "playoffs", num, tiebreaker, ..] => {
                 (event_code.to_string(), MatchKey::Playoff { num: num.parse()?, tiebreaker: tiebreaker.parse()? })
             }
+            [event_code, "playoffs", num] => {
+                (event_code.to_string(), MatchKey::Playoff { num: num.parse()?, tiebreaker: 1 })
+            }
             _ => {
                 tracing::warn!("Unparsable FTC-Events URL `{url}`, possibly a bug!!!");
                 anyhow::bail!("Unparseable FTC-Events url");
@@ -83,8 +251,61 @@ impl FTCEventsQR {
         };
 
         Ok(Self {
+            season,
             event_code,
             key: match_type,
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qr(season: Option<u32>, key: MatchKey) -> FTCEventsQR {
+        FTCEventsQR { season, event_code: "USCALAS".to_string(), key }
+    }
+
+    #[test]
+    fn ftc_events_urls() {
+        let q12 = MatchKey::Qualification { num: 12 };
+        let cases = [
+            ("https://ftc.events/USCALAS/qualifications/12", qr(None, q12)),
+            ("https://ftc.events/2025/USCALAS/qualifications/12", qr(Some(2025), q12)),
+            ("http://ftc.events/2025/USCALAS/qualifications/12", qr(Some(2025), q12)),
+            ("https://www.ftc.events/2025/USCALAS/qualifications/12", qr(Some(2025), q12)),
+            ("https://ftc.events/2025/USCALAS/qualifications/12?tab=details", qr(Some(2025), q12)),
+            ("https://ftc.events/2025/USCALAS/qualifications/12/", qr(Some(2025), q12)),
+            ("  https://ftc.events/2025/USCALAS/qualifications/12\n", qr(Some(2025), q12)),
+            ("https://ftc.events/2025/USCALAS/practice/4", qr(Some(2025), MatchKey::Practice { num: 4 })),
+            ("https://ftc.events/2019/USCALAS/semifinals/1/2", qr(Some(2019), MatchKey::Semifinal { series: 1, num: 2 })),
+            ("https://ftc.events/2019/USCALAS/finals/2", qr(Some(2019), MatchKey::Final { num: 2 })),
+            ("https://ftc.events/2025/USCALAS/playoffs/3", qr(Some(2025), MatchKey::Playoff { num: 3, tiebreaker: 1 })),
+            ("https://ftc.events/2025/USCALAS/playoffs/3/2", qr(Some(2025), MatchKey::Playoff { num: 3, tiebreaker: 2 })),
+        ];
+        for (url, want) in cases {
+            let got = FTCEventsQR::new(url).unwrap_or_else(|e| panic!("{url}: {e}"));
+            assert_eq!(got, want, "{url}");
+        }
+    }
+
+    #[test]
+    fn ftc_events_urls_rejected() {
+        let cases = [
+            "",
+            "not a url",
+            "ftp://ftc.events/2025/USCALAS/qualifications/12",
+            "https://example.com/2025/USCALAS/qualifications/12",
+            "https://ftc.events.example.com/2025/USCALAS/qualifications/12",
+            "https://ftc.events/",
+            "https://ftc.events/2025/USCALAS",
+            "https://ftc.events/2025/USCALAS/rankings",
+            "https://ftc.events/2025/qualifications/12",
+            "https://ftc.events/2025/USCALAS/qualifications/twelve",
+            "https://ftc.events/2025/USCALAS/semifinals/1",
+        ];
+        for url in cases {
+            assert!(FTCEventsQR::new(url).is_err(), "{url}");
+        }
+    }
+}
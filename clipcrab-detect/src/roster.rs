@@ -0,0 +1,168 @@
+//! Event rosters, for checking OCR'd team numbers against who is actually at the event.
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::utils::edit_distance;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Roster {
+    teams: BTreeSet<u64>,
+}
+
+/// Outcome of checking a read team number against a roster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeamResolution {
+    /// On the roster as read.
+    Exact(u64),
+    /// Snapped to the single closest roster entry.
+    Corrected { read: u64, team: u64, distance: usize },
+    /// Nothing on the roster is close enough, or several entries are equally close.
+    Unresolved(u64),
+}
+
+impl TeamResolution {
+    /// Best guess at the team number.
+    pub fn team(&self) -> u64 {
+        match *self {
+            TeamResolution::Exact(team) => team,
+            TeamResolution::Corrected { team, .. } => team,
+            TeamResolution::Unresolved(read) => read,
+        }
+    }
+}
+
+impl Roster {
+    pub fn new(teams: impl IntoIterator<Item = u64>) -> Self {
+        Self { teams: teams.into_iter().collect() }
+    }
+
+    /// Loads a roster file, see [`Roster::parse`].
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses either a plain list of team numbers separated by whitespace or commas, or JSON.
+    ///
+    /// JSON can be an array of team numbers, an array of team objects with a `teamNumber` field,
+    /// or an ftc.events team list (`{"teams": [{"teamNumber": 1234, ...}, ...]}`).
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if s.starts_with('[') || s.starts_with('{') {
+            let json: serde_json::Value = serde_json::from_str(s)?;
+            let list = match &json {
+                serde_json::Value::Object(obj) => obj.get("teams").ok_or(anyhow::anyhow!("No `teams` field in roster JSON"))?,
+                _ => &json,
+            };
+            let list = list.as_array().ok_or(anyhow::anyhow!("Roster JSON is not a list"))?;
+            let teams = list
+                .iter()
+                .map(|t| t.get("teamNumber").unwrap_or(t).as_u64().ok_or(anyhow::anyhow!("Not a team number: {t}")))
+                .collect::<anyhow::Result<BTreeSet<u64>>>()?;
+            return Ok(Self { teams });
+        }
+
+        let teams = s
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+            .map(|t| t.parse::<u64>())
+            .collect::<Result<BTreeSet<u64>, _>>()?;
+        Ok(Self { teams })
+    }
+
+    pub fn contains(&self, team: u64) -> bool {
+        self.teams.contains(&team)
+    }
+
+    pub fn len(&self) -> usize {
+        self.teams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.teams.is_empty()
+    }
+
+    /// Snaps a read team number to the roster by edit distance on its digits.
+    ///
+    /// OCR mostly gets one digit wrong or drops one, so we allow one edit (two on 5+ digit numbers),
+    /// and only if exactly one roster entry is that close.
+    pub fn resolve(&self, read: u64) -> TeamResolution {
+        if self.teams.contains(&read) {
+            return TeamResolution::Exact(read);
+        }
+        let read_str = read.to_string();
+        let max_distance = if read_str.len() >= 5 { 2 } else { 1 };
+
+        let mut best: Option<(usize, u64)> = None;
+        let mut ambiguous = false;
+        for team in self.teams.iter() {
+            let distance = edit_distance(&read_str, &team.to_string());
+            match best {
+                Some((best_distance, _)) if distance > best_distance => {}
+                Some((best_distance, _)) if distance == best_distance => ambiguous = true,
+                _ => {
+                    best = Some((distance, *team));
+                    ambiguous = false;
+                }
+            }
+        }
+
+        match best {
+            Some((distance, team)) if distance <= max_distance && !ambiguous => {
+                TeamResolution::Corrected { read, team, distance }
+            }
+            _ => TeamResolution::Unresolved(read),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_exact() {
+        let roster = Roster::new([1234, 5678]);
+        assert_eq!(roster.resolve(1234), TeamResolution::Exact(1234));
+    }
+
+    #[test]
+    fn resolve_substituted_digit() {
+        let roster = Roster::new([1234, 5678]);
+        assert_eq!(roster.resolve(1284), TeamResolution::Corrected { read: 1284, team: 1234, distance: 1 });
+        // dropped digit
+        assert_eq!(roster.resolve(567), TeamResolution::Corrected { read: 567, team: 5678, distance: 1 });
+        assert_eq!(roster.resolve(1284).team(), 1234);
+    }
+
+    #[test]
+    fn resolve_two_edits_only_on_long_numbers() {
+        let roster = Roster::new([1234, 16000]);
+        assert_eq!(roster.resolve(16880), TeamResolution::Corrected { read: 16880, team: 16000, distance: 2 });
+        assert_eq!(roster.resolve(1884), TeamResolution::Unresolved(1884));
+    }
+
+    #[test]
+    fn resolve_ambiguous_tie() {
+        let roster = Roster::new([1234, 1235]);
+        assert_eq!(roster.resolve(1236), TeamResolution::Unresolved(1236));
+        assert_eq!(roster.resolve(1236).team(), 1236);
+    }
+
+    #[test]
+    fn resolve_not_on_roster() {
+        let roster = Roster::new([1234, 5678]);
+        assert_eq!(roster.resolve(9999), TeamResolution::Unresolved(9999));
+        assert_eq!(Roster::default().resolve(1234), TeamResolution::Unresolved(1234));
+    }
+
+    #[test]
+    fn parse_formats() {
+        let want = Roster::new([1234, 5678]);
+        assert_eq!(Roster::parse("1234\n5678\n").unwrap(), want);
+        assert_eq!(Roster::parse("1234, 5678").unwrap(), want);
+        assert_eq!(Roster::parse("[1234, 5678]").unwrap(), want);
+        assert_eq!(Roster::parse(r#"[{"teamNumber": 1234}, {"teamNumber": 5678}]"#).unwrap(), want);
+        assert_eq!(Roster::parse(r#"{"teams": [{"teamNumber": 1234, "nameShort": "a"}, {"teamNumber": 5678}]}"#).unwrap(), want);
+        assert!(Roster::parse("1234 abc").is_err());
+    }
+}
@@ -1 +1,17 @@
-pub mod s2025_decode;
\ No newline at end of file
+// 1080p-relative coordinates
+macro_rules! scale_x {
+    ($name:ident = $value:expr) => {
+        const $name: f64 = ($value as f64) / 1920.0;
+    };
+}
+
+// 1080p-relative coordinates
+macro_rules! scale_y {
+    ($name:ident = $value:expr) => {
+        const $name: f64 = ($value as f64) / 1080.0;
+    };
+}
+
+pub mod s2025_decode;
+pub mod s2025_decode_results;
+pub mod s2025_decode_ceremony;
@@ -11,22 +11,9 @@
 //! 
 use opencv::{core::{self as cvcore, Mat, MatTraitConst}, imgcodecs, imgproc};
 
-use crate::{matchers::{MatchPhaseDetector, TemplateMatcher}, ocr::Ocr, utils::{self, Point, Size}};
-use crate::{MatchDisplayInfo, MatchDetection};
-
-// 1080p-relative coordinates
-macro_rules! scale_x {
-    ($name:ident = $value:expr) => {
-        const $name: f64 = ($value as f64) / 1920.0;
-    };
-}
-
-// 1080p-relative coordinates
-macro_rules! scale_y {
-    ($name:ident = $value:expr) => {
-        const $name: f64 = ($value as f64) / 1080.0;
-    };
-}
+use crate::{locale::Locale, matchers::{MatchPhaseDetector, TemplateMatcher}, ocr::Ocr, qr::QrDetector, roster::{Roster, TeamResolution}, utils::{self, Point, Size}};
+use crate::{DisplayLayout, FrameClass, MatchDisplayInfo, MatchDetection, MatchPhase};
+use super::{s2025_decode_ceremony, s2025_decode_results::{DecodeResultsDetector, LOGO_MAX_Y}};
 
 // Distance from the logo template to the right edge of the match display.
 scale_x!(LOGO_DIST_TO_RIGHT_EDGE = 72);
@@ -39,6 +26,11 @@ scale_x!(MATCH_NAME_X = 980);
 // Width of match name ROI
 scale_x!(MATCH_NAME_WIDTH = 670);
 
+// X-position of the event name relative to the left side of the screen.
+scale_x!(EVENT_NAME_X = 20);
+// Width of event name ROI, up to the match name
+scale_x!(EVENT_NAME_WIDTH = 940);
+
 // Height of the scoring display proper.
 scale_y!(SCORING_DISPLAY_HEIGHT = 180);
 
@@ -54,6 +46,9 @@ const SCORING_BAR_LIP_HEIGHT: f64 = 30.0 / 180.0;
 // Threshold at which a scoring box is considered blue.
 const SCORE_BLUE_THRESHOLD: f64 = 0.7;
 
+// Below this much red or blue, the right scoring box isn't there and we're looking at a remote event display.
+const REMOTE_COLOR_THRESHOLD: f64 = 0.2;
+
 // X-offset from left of scoring display to timer ROI
 scale_x!(TIMER_X = 860);
 // Width of timer text ROI
@@ -66,6 +61,13 @@ scale_y!(TIMER_HEIGHT = 85);
 // Height of timer phase ROI
 scale_y!(TIMER_PHASE_HEIGHT = 56);
 
+// Frames with less gray-level spread than this are idle screens.
+const IDLE_MAX_STDDEV: f64 = 8.0;
+
+// Frames that aren't anything else get the full-frame QR search once every this many,
+// for results screens scaled or moved so far that the title bar logo doesn't match either.
+const QR_SEARCH_EVERY: u32 = 5;
+
 
 #[derive(Debug)]
 pub struct DecodeDetector {
@@ -75,6 +77,7 @@ pub struct DecodeDetector {
     match_name_ocr: Ocr,
     number_ocr: Ocr,
     match_time_ocr: Ocr,
+    roster: Option<Roster>,
 }
 
 impl DecodeDetector {
@@ -90,6 +93,82 @@ impl DecodeDetector {
             match_name_ocr: Ocr::new(Some("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ")),
             number_ocr: Ocr::new(Some("0123456789")),
             match_time_ocr: Ocr::new(Some("0123456789:")),
+            roster: None,
+        }
+    }
+
+    /// Reads match and event names from localized scoring software.
+    ///
+    /// Match names are passed on as read, parse them with [`crate::match_name::parse_localized`].
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.match_name_ocr = Ocr::new(Some(&locale.ocr_chars()));
+        self
+    }
+
+    /// Checks read team numbers against an event roster.
+    pub fn with_roster(mut self, roster: Roster) -> Self {
+        self.roster = Some(roster);
+        self
+    }
+
+    /// Snaps read teams to the roster, if we have one. Returns the teams we couldn't snap.
+    fn resolve_teams(&self, teams: &mut [u64]) -> Vec<u64> {
+        let Some(roster) = &self.roster else {
+            return Vec::new();
+        };
+        let mut unresolved = Vec::new();
+        for team in teams.iter_mut().filter(|t| **t != 0) {
+            match roster.resolve(*team) {
+                TeamResolution::Exact(_) => {}
+                TeamResolution::Corrected { read, team: corrected, distance } => {
+                    tracing::trace!("Corrected team {read} -> {corrected} (distance {distance})");
+                    *team = corrected;
+                }
+                TeamResolution::Unresolved(read) => {
+                    tracing::debug!("Team {read} is not on the roster");
+                    unresolved.push(read);
+                }
+            }
+        }
+        unresolved
+    }
+
+    /// Reads a list of team numbers, one per line. Lines we can't read become team 0.
+    fn read_teams(&self, roi: &Mat) -> Vec<u64> {
+        self.number_ocr
+            .extract_text(roi)
+            .split('\n')
+            .filter(|line| !line.trim().is_empty())
+            .map(|f| f.trim().parse::<u64>().unwrap_or(0))
+            .collect()
+    }
+
+    /// Tells the usual two-alliance display from the remote event one.
+    ///
+    /// Remote displays only have the one score box on the left, so we check for a red or blue box on the right.
+    fn detect_layout(&self, scoring_display: &Mat) -> DisplayLayout {
+        let right_box = utils::relative_extract_roi(
+            scoring_display,
+            None,
+            Point::new(0.5 + TIMER_WIDTH / 2.0, SCORING_BAR_LIP_HEIGHT),
+            Size::new(0.5 - TIMER_WIDTH / 2.0 - ALLIANCE_SCORING_WIDTH - ALLIANCE_NUMBER_WIDTH, 1.0 - SCORING_BAR_LIP_HEIGHT)
+        );
+        let hsv = utils::cvt_color(&right_box, imgproc::COLOR_RGB2HSV);
+        let area = right_box.size().unwrap().area() as f64;
+        let fraction = |lower: [u8; 3], upper: [u8; 3]| {
+            let mut thr = Mat::default();
+            cvcore::in_range(&hsv, &lower, &upper, &mut thr).unwrap();
+            cvcore::count_non_zero(&thr).unwrap() as f64 / area
+        };
+        // red wraps around the hue circle
+        let red = fraction([0, 100, 50], [10, 255, 255]) + fraction([170, 100, 50], [180, 255, 255]);
+        let blue = fraction([98, 100, 50], [108, 255, 255]);
+        tracing::trace!("Right score box red {red}, blue {blue}");
+
+        if red.max(blue) < REMOTE_COLOR_THRESHOLD {
+            DisplayLayout::Remote
+        } else {
+            DisplayLayout::Traditional
         }
     }
 
@@ -101,6 +180,20 @@ impl DecodeDetector {
             Point::new(ALLIANCE_SCORING_WIDTH, SCORING_BAR_LIP_HEIGHT),
             Size::new(ALLIANCE_NUMBER_WIDTH, 1.0 - SCORING_BAR_LIP_HEIGHT)
         );
+        let mut left_teams = self.read_teams(&left_alliance);
+
+        if self.detect_layout(scoring_display) == DisplayLayout::Remote {
+            // remote events only list the one team, where the left alliance would be
+            let unresolved_teams = self.resolve_teams(&mut left_teams);
+            return MatchDisplayInfo {
+                layout: DisplayLayout::Remote,
+                red_alliance: left_teams,
+                blue_alliance: Vec::new(),
+                display_flipped: false,
+                unresolved_teams,
+            };
+        }
+
         // Right alliance
         let right_alliance = utils::relative_extract_roi(
             scoring_display, 
@@ -108,16 +201,9 @@ impl DecodeDetector {
             Point::new(1.0 - ALLIANCE_SCORING_WIDTH - ALLIANCE_NUMBER_WIDTH, SCORING_BAR_LIP_HEIGHT),
             Size::new(ALLIANCE_NUMBER_WIDTH, 1.0 - SCORING_BAR_LIP_HEIGHT)
         );
-        let left_teams = self.number_ocr
-            .extract_text(&left_alliance)
-            .split('\n')
-            .map(|f| f.parse::<u64>().unwrap_or(0))
-            .collect::<Vec<u64>>();
-        let right_teams = self.number_ocr
-            .extract_text(&right_alliance)
-            .split('\n')
-            .map(|f| f.parse::<u64>().unwrap_or(0))
-            .collect::<Vec<u64>>();
+        let mut right_teams = self.read_teams(&right_alliance);
+        let mut unresolved_teams = self.resolve_teams(&mut left_teams);
+        unresolved_teams.extend(self.resolve_teams(&mut right_teams));
 
         // To determine which alliances are red or blue, we need to determine whether blue is flipped to the other side or not.
         // We do this by determining how much blue there is in the left total score box,
@@ -139,19 +225,27 @@ impl DecodeDetector {
         tracing::trace!("Display flipped confidence score: {blue_score}");
 
         if blue_score > SCORE_BLUE_THRESHOLD {
-            MatchDisplayInfo { red_alliance: right_teams, blue_alliance: left_teams, display_flipped: true }
+            MatchDisplayInfo { layout: DisplayLayout::Traditional, red_alliance: right_teams, blue_alliance: left_teams, display_flipped: true, unresolved_teams }
         } else {
-            MatchDisplayInfo { red_alliance: left_teams, blue_alliance: right_teams, display_flipped: false }
+            MatchDisplayInfo { layout: DisplayLayout::Traditional, red_alliance: left_teams, blue_alliance: right_teams, display_flipped: false, unresolved_teams }
         }
 
 
     }
 
     pub fn detect(&self, frame: &Mat) -> Option<MatchDetection> {
+        match self.classify(frame) {
+            FrameClass::InMatch(detection) => Some(detection),
+            _ => None,
+        }
+    }
+
+    /// Like [`DecodeDetector::detect`], but also tells previews apart from everything else.
+    pub fn classify(&self, frame: &Mat) -> FrameClass {
         // Step 1: find the logo.
         let Some(logo) = self.logo_detector.matches(frame, None) else {
             tracing::trace!("No match found!");
-            return None;
+            return FrameClass::Unknown;
         };
         tracing::trace!("Found logo!");
         let frame_size = frame.size().unwrap();
@@ -169,9 +263,14 @@ impl DecodeDetector {
         );
 
         // Step 3: check if the match is a preview match
-        if self.not_a_preview_detector.matches(frame, None).is_none() {
-            tracing::trace!("Found scoring display, but this is not a match!");
-            return None;
+        // Full-screen displays have no score box either, but their logo is up in the title bar.
+        let preview = self.not_a_preview_detector.matches(frame, None).is_none();
+        if preview && logo.rel_y <= LOGO_MAX_Y {
+            tracing::trace!("No score box and the logo is in a title bar, not a preview");
+            return FrameClass::Unknown;
+        }
+        if preview {
+            tracing::trace!("Found scoring display, but this is a match preview!");
         }
 
         // Step 4: extract the match name
@@ -186,9 +285,36 @@ impl DecodeDetector {
         tracing::trace!("Detected match name: {match_name:?}");
         if match_name.contains("Example") {
             // skip the example match display
-            return None;
+            return FrameClass::Unknown;
+        }
+        // Step 5: extract the event name, which sits left of the match name
+        let roi = utils::relative_extract_roi(
+            frame,
+            None,
+            Point::new(EVENT_NAME_X, match_display_tl.y - NAME_BAR_HEIGHT + 10.0/1080.0),
+            Size::new(EVENT_NAME_WIDTH, NAME_BAR_HEIGHT - 15.0/1080.0)
+        );
+        let event_name = self.match_name_ocr.extract_text(&roi).split_whitespace().collect::<Vec<_>>().join(" ");
+        tracing::trace!("Detected event name: {event_name:?}");
+        let event_name = (!event_name.is_empty()).then_some(event_name);
+
+        if preview {
+            // previews have no clock, but the teams are in the same spot
+            let display_info = self.extract_display_data(&scoring_display);
+            tracing::trace!("Preview display info: {display_info:?}");
+            if !teams_read(&display_info) {
+                return FrameClass::Unknown;
+            }
+            return FrameClass::Preview(MatchDetection {
+                name: match_name,
+                event_name,
+                time: 150,
+                phase: MatchPhase::Preview,
+                display_info,
+            });
         }
-        // Step 5: extract the match time
+
+        // Step 6: extract the match time
         let roi = utils::relative_extract_roi(
             &scoring_display,
             Some(frame_size),
@@ -201,9 +327,11 @@ impl DecodeDetector {
         //self.match_time_ocr.extract_text_debug(&roi);
 
         tracing::trace!("Detected match time: {match_time:?}");
-        let match_seconds = utils::match_time_to_seconds(&match_time)?;
+        let Some(match_seconds) = utils::match_time_to_seconds(&match_time) else {
+            return FrameClass::Unknown;
+        };
         tracing::trace!("Detected match seconds: {match_seconds}");
-        // Step 6: determine the phase of the match
+        // Step 7: determine the phase of the match
         let roi = utils::relative_extract_roi(
             &scoring_display,
             Some(frame_size),
@@ -211,15 +339,18 @@ impl DecodeDetector {
             Size::new(TIMER_WIDTH, TIMER_PHASE_HEIGHT)
         );
         utils::imwrite("target/phase_roi.png", &roi);
-        let phase = self.match_phase_detector.detect_match_phase(&roi, frame_size.into(), match_seconds)?;
+        let Some(phase) = self.match_phase_detector.detect_match_phase(&roi, frame_size.into(), match_seconds) else {
+            return FrameClass::Unknown;
+        };
         tracing::trace!("Detected match phase: {phase:?}");
 
-        // Step 7: extract the teams in this match
+        // Step 8: extract the teams in this match
         let display_info = self.extract_display_data(&scoring_display);
         tracing::trace!("Display info: {display_info:?}");
 
-        Some(MatchDetection {
+        FrameClass::InMatch(MatchDetection {
             name: match_name,
+            event_name,
             time: match_seconds,
             phase,
             display_info
@@ -227,8 +358,157 @@ impl DecodeDetector {
     }
 }
 
-impl crate::Detector for DecodeDetector {
-    fn detect(&self, frame: &Mat) -> Option<MatchDetection> {
-        self.detect(frame)
+/// Whether every team slot on a display was read as a number.
+fn teams_read(info: &MatchDisplayInfo) -> bool {
+    let blue_ok = info.layout == DisplayLayout::Remote || !info.blue_alliance.is_empty();
+    !info.red_alliance.is_empty() && blue_ok && info.red_alliance.iter().chain(&info.blue_alliance).all(|team| *team != 0)
+}
+
+/// Everything we look for on a DECODE stream.
+///
+/// The scoring display goes first since it's on screen the most, then results screens by QR code,
+/// then anything with a title bar: results screens without QR codes, alliance selection, and awards.
+#[derive(Debug)]
+pub struct DecodeFrameDetector {
+    display: DecodeDetector,
+    qr: QrDetector,
+    results: DecodeResultsDetector,
+    frames_since_search: u32,
+}
+
+impl DecodeFrameDetector {
+    pub fn new(display: DecodeDetector) -> Self {
+        Self {
+            display,
+            qr: QrDetector::new(),
+            results: DecodeResultsDetector::new(),
+            frames_since_search: QR_SEARCH_EVERY,
+        }
+    }
+
+    /// Reads results screen titles from localized scoring software. Set the display's locale on the [`DecodeDetector`].
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.results = self.results.with_locale(locale);
+        self
+    }
+
+    fn qr_results(&self, frame: &Mat, det: crate::qr::QrDetection) -> crate::ResultsScreen {
+        let mut screen: crate::ResultsScreen = det.qr.into();
+        screen.scores = self.results.read_scores(frame);
+        screen
+    }
+}
+
+impl crate::Detector for DecodeFrameDetector {
+    fn detect(&mut self, frame: &Mat) -> FrameClass {
+        match self.display.classify(frame) {
+            FrameClass::Unknown => {}
+            class => return class,
+        }
+        if let Some(det) = self.qr.detect(frame) {
+            return FrameClass::Results(self.qr_results(frame, det));
+        }
+        let title = self.results.read_title(frame);
+        // the full-frame QR search is far too slow for every frame, so it's for full-screen displays and the odd sample
+        self.frames_since_search += 1;
+        if title.is_some() || self.frames_since_search >= QR_SEARCH_EVERY {
+            self.frames_since_search = 0;
+            if let Some(det) = self.qr.search(frame) {
+                return FrameClass::Results(self.qr_results(frame, det));
+            }
+        }
+        if let Some(title) = title {
+            // some events turn the QR codes off, so fall back to reading the title
+            if let Some(screen) = self.results.parse_title(frame, &title) {
+                return FrameClass::Results(screen);
+            }
+            if let Some(class) = s2025_decode_ceremony::classify_title(&title) {
+                return class;
+            }
+        }
+        if utils::is_blank(frame, IDLE_MAX_STDDEV) {
+            return FrameClass::Idle;
+        }
+        FrameClass::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Detector, MatchKey};
+    use opencv::core::{CV_8UC3, Rect, Scalar};
+    use opencv::prelude::*;
+
+    /// A plain gray 1080p frame.
+    fn gray_frame() -> Mat {
+        Mat::new_rows_cols_with_default(1080, 1920, CV_8UC3, Scalar::all(128.0)).unwrap()
+    }
+
+    /// Pastes the season logo into `frame` with its top-left corner at (x, y).
+    fn paste_logo(frame: &mut Mat, x: i32, y: i32) {
+        let logo = imgcodecs::imdecode(include_bytes!("../../templates/s2025_decode.png"), imgcodecs::IMREAD_GRAYSCALE).unwrap();
+        let logo = utils::cvt_color(&logo, imgproc::COLOR_GRAY2RGB);
+        let size = logo.size().unwrap();
+        let mut roi = Mat::roi_mut(frame, Rect::new(x, y, size.width, size.height)).unwrap();
+        logo.copy_to(&mut *roi).unwrap();
+    }
+
+    /// Draws a QR code for `url` with its top-left corner at (x, y), `module` pixels per module.
+    fn paste_qr(frame: &mut Mat, url: &str, x: i32, y: i32, module: i32) {
+        let code = qrcode::QrCode::new(url).unwrap();
+        let width = code.width() as i32;
+        // quiet zone of 4 modules
+        let quiet = Rect::new(x - 4 * module, y - 4 * module, (width + 8) * module, (width + 8) * module);
+        imgproc::rectangle(frame, quiet, Scalar::all(255.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == qrcode::Color::Dark {
+                let (mx, my) = (i as i32 % width, i as i32 / width);
+                let rect = Rect::new(x + mx * module, y + my * module, module, module);
+                imgproc::rectangle(frame, rect, Scalar::all(0.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn qr_search_without_logo() {
+        // larger than usual and nowhere near the default spot, on a frame with no title bar
+        let mut frame = gray_frame();
+        paste_qr(&mut frame, "https://ftc.events/2025/USCALAS/qualifications/12", 1400, 160, 9);
+        let mut detector = DecodeFrameDetector::new(DecodeDetector::new());
+
+        let is_q12 = |class: &FrameClass| matches!(
+            class,
+            FrameClass::Results(screen) if screen.key == MatchKey::Qualification { num: 12 } && screen.event_code.as_deref() == Some("USCALAS")
+        );
+        let found = (0..QR_SEARCH_EVERY).map(|_| detector.detect(&frame)).any(|class| is_q12(&class));
+        assert!(found, "no results screen in {QR_SEARCH_EVERY} frames");
+        // and from then on the cheap check knows where to look
+        assert!(is_q12(&detector.detect(&frame)));
+    }
+
+    #[test]
+    fn title_bar_logo_is_not_a_preview() {
+        // a results screen: logo in the title bar and no blue score box anywhere
+        let mut frame = gray_frame();
+        paste_logo(&mut frame, 40, 20);
+        assert_eq!(DecodeDetector::new().classify(&frame), FrameClass::Unknown);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn preview_needs_every_team() {
+        let info = |layout, red: &[u64], blue: &[u64]| MatchDisplayInfo {
+            layout,
+            red_alliance: red.to_vec(),
+            blue_alliance: blue.to_vec(),
+            display_flipped: false,
+            unresolved_teams: Vec::new(),
+        };
+        assert!(teams_read(&info(DisplayLayout::Traditional, &[1234, 5678], &[9012, 3456])));
+        assert!(teams_read(&info(DisplayLayout::Remote, &[1234], &[])));
+        assert!(!teams_read(&info(DisplayLayout::Traditional, &[1234, 5678], &[])));
+        assert!(!teams_read(&info(DisplayLayout::Traditional, &[1234, 0], &[9012, 3456])));
+        assert!(!teams_read(&info(DisplayLayout::Traditional, &[], &[])));
+        assert!(!teams_read(&info(DisplayLayout::Remote, &[], &[])));
+    }
+}
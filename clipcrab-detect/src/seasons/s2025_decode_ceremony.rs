@@ -0,0 +1,102 @@
+//! Decode alliance selection and awards screens.
+//!
+//! Both are full-screen displays with the same title bar as the results screen,
+//! so we go by the title text from [`DecodeResultsDetector::read_title`](super::s2025_decode_results::DecodeResultsDetector::read_title),
+//! which only reads the title bar, and only once the season logo has been found in it.
+use crate::{FrameClass, utils};
+
+// Phrases the title has to contain, word for word and in order.
+// Translated titles keep their own word order, e.g. "Selección de Alianzas" comes out as "selection of alliance".
+const ALLIANCE_SELECTION: &[&[&str]] = &[&["alliance", "selection"], &["selection", "of", "alliance"], &["selection", "alliance"]];
+const AWARDS: &[&[&str]] = &[&["awards"], &["award"]];
+
+// Words this short have to be read exactly; "award" is one edit from "aware" and "sward".
+const MAX_EXACT_LEN: usize = 6;
+
+/// Tells alliance selection and awards titles apart from everything else.
+pub fn classify_title(title: &str) -> Option<FrameClass> {
+    let words = title
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<String>>();
+
+    if ALLIANCE_SELECTION.iter().any(|phrase| has_phrase(&words, phrase)) {
+        Some(FrameClass::AllianceSelection)
+    } else if AWARDS.iter().any(|phrase| has_phrase(&words, phrase)) {
+        Some(FrameClass::Awards)
+    } else {
+        None
+    }
+}
+
+fn has_phrase(words: &[String], phrase: &[&str]) -> bool {
+    words
+        .windows(phrase.len())
+        .any(|window| window.iter().zip(phrase).all(|(word, want)| close_enough(word, want)))
+}
+
+/// Whether OCR'd `word` is `want`, allowing a quarter of the letters wrong on longer words.
+fn close_enough(word: &str, want: &str) -> bool {
+    let len = want.chars().count();
+    let max_edits = if len <= MAX_EXACT_LEN { 0 } else { len / 4 };
+    utils::edit_distance(word, want) <= max_edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+
+    #[test]
+    fn alliance_selection_titles() {
+        for title in ["Alliance Selection", "ALLIANCE SELECTION", "Alliance Selecti0n", "AIliance Selection", "Edison Alliance Selection"] {
+            assert_eq!(classify_title(title), Some(FrameClass::AllianceSelection), "{title}");
+        }
+    }
+
+    #[test]
+    fn awards_titles() {
+        for title in ["Awards", "AWARDS", "Inspire Award", "Think Award 2nd Place", "Winning Alliance Award", "Award:"] {
+            assert_eq!(classify_title(title), Some(FrameClass::Awards), "{title}");
+        }
+    }
+
+    #[test]
+    fn other_titles() {
+        let titles = [
+            "",
+            "Qualification 12 Results",
+            "Playoff Match 3 Results",
+            // the words have to be next to each other
+            "Alliance 1",
+            "Selection 1 Alliance",
+            "Alliance Captain Selection",
+            // short words have to be exact
+            "Aware",
+            "Sward",
+            "Toward",
+            "Forward Progress",
+            "Ward 3",
+        ];
+        for title in titles {
+            assert_eq!(classify_title(title), None, "{title}");
+        }
+    }
+
+    #[test]
+    fn localized_titles() {
+        let cases = [
+            (Locale::Spanish, "Selección de Alianzas", Some(FrameClass::AllianceSelection)),
+            (Locale::Portuguese, "Seleção de Alianças", Some(FrameClass::AllianceSelection)),
+            (Locale::Romanian, "Selecția Alianțelor", Some(FrameClass::AllianceSelection)),
+            (Locale::Spanish, "Premios", Some(FrameClass::Awards)),
+            (Locale::Portuguese, "Prêmios", Some(FrameClass::Awards)),
+            (Locale::Romanian, "Premii", Some(FrameClass::Awards)),
+            (Locale::Chinese, "颁奖", Some(FrameClass::Awards)),
+        ];
+        for (locale, title, want) in cases {
+            assert_eq!(classify_title(&locale.translate(title)), want, "{locale:?} {title}");
+        }
+    }
+}
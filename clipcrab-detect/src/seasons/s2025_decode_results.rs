@@ -0,0 +1,196 @@
+//! Decode results screen matcher, for events that run the scoring system with QR codes turned off.
+//!
+//! The full-screen results screen has a title bar across the top with the season logo and a title like
+//! `Qualification 12 Results`. We look for the logo in that bar, OCR the title, and parse the match name out of it.
+//! Without the QR code there is no event code, so the [`ResultsScreen`] we hand back only has the match key.
+//!
+//! Below the title, each alliance gets a panel with its total score on top and the auto/teleop/penalty breakdown under it.
+//! Red is normally on the left; like the scoring display, the results screen can be flipped.
+use opencv::{core::{self as cvcore, Mat, MatTraitConst}, imgcodecs, imgproc};
+
+use crate::{AllianceScore, locale::Locale, ResultsScores, ResultsScreen, match_name, matchers::TemplateMatcher, ocr::Ocr, utils::{self, Point, Size}};
+
+// Height of the results screen title bar.
+scale_y!(TITLE_BAR_HEIGHT = 120);
+
+// Horizontal margin around the title text.
+scale_x!(TITLE_MARGIN_X = 20);
+
+// The logo has to sit this high up to be the title bar and not the in-match scoring display.
+pub(super) const LOGO_MAX_Y: f64 = TITLE_BAR_HEIGHT;
+
+// Lowest match name confidence we accept from the title.
+const MIN_TITLE_CONFIDENCE: f64 = 0.75;
+
+// Word that ends the title; OCR gets some slack on it.
+const RESULTS_WORD: &str = "results";
+
+// X-position of the left alliance panel.
+scale_x!(LEFT_PANEL_X = 60);
+// X-position of the right alliance panel.
+scale_x!(RIGHT_PANEL_X = 980);
+// Width of an alliance panel
+scale_x!(PANEL_WIDTH = 880);
+// Y-position of the alliance panels
+scale_y!(PANEL_Y = 140);
+
+// Height of the total score box at the top of a panel
+scale_y!(TOTAL_HEIGHT = 200);
+// Y-offset from the top of a panel to the breakdown rows
+scale_y!(BREAKDOWN_Y = 220);
+// Height of the breakdown rows
+scale_y!(BREAKDOWN_HEIGHT = 300);
+
+// Threshold at which the left total score box is considered blue.
+const SCORE_BLUE_THRESHOLD: f64 = 0.5;
+
+// Labels of the breakdown rows, as OCR would hopefully read them.
+const AUTO_LABELS: &[&str] = &["auto", "autonomous"];
+const TELEOP_LABELS: &[&str] = &["teleop", "driver"];
+const PENALTY_LABELS: &[&str] = &["penalty", "penalties", "foul", "fouls"];
+
+#[derive(Debug)]
+pub struct DecodeResultsDetector {
+    logo_detector: TemplateMatcher,
+    title_ocr: Ocr,
+    number_ocr: Ocr,
+    locale: Locale,
+}
+
+impl DecodeResultsDetector {
+    pub fn new() -> Self {
+        let template_img = imgcodecs::imdecode(include_bytes!("../../templates/s2025_decode.png"), imgcodecs::IMREAD_GRAYSCALE).unwrap();
+
+        Self {
+            logo_detector: TemplateMatcher::new(template_img, Size::res_1080p(), Size::new(1280.0, 720.0), 0.7),
+            title_ocr: Ocr::new(Some("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789- ")),
+            number_ocr: Ocr::new(Some("0123456789")),
+            locale: Locale::English,
+        }
+    }
+
+    /// Reads titles from localized scoring software.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.title_ocr = Ocr::new(Some(&format!("{}-", locale.ocr_chars())));
+        self.locale = locale;
+        self
+    }
+
+    pub fn detect(&self, frame: &Mat) -> Option<ResultsScreen> {
+        let title = self.read_title(frame)?;
+        self.parse_title(frame, &title)
+    }
+
+    /// Reads the title bar of a full-screen display, if there is one.
+    ///
+    /// Alliance selection and awards screens share the title bar, see [`super::s2025_decode_ceremony`].
+    pub fn read_title(&self, frame: &Mat) -> Option<String> {
+        // Step 1: find the logo in the title bar.
+        let Some(logo) = self.logo_detector.matches(frame, None) else {
+            tracing::trace!("No logo found!");
+            return None;
+        };
+        if logo.rel_y > LOGO_MAX_Y {
+            tracing::trace!("Found logo, but too low for a results screen");
+            return None;
+        }
+
+        // Step 2: read the title.
+        let roi = utils::relative_extract_roi(
+            frame,
+            None,
+            Point::new(TITLE_MARGIN_X, 0.0),
+            Size::new(1.0 - 2.0 * TITLE_MARGIN_X, TITLE_BAR_HEIGHT)
+        );
+        let title = self.title_ocr.extract_text(&roi);
+        tracing::trace!("Detected title: {title:?}");
+        // everything after this only knows English
+        Some(self.locale.translate(&title))
+    }
+
+    /// Picks the match out of a results screen title from [`DecodeResultsDetector::read_title`].
+    pub fn parse_title(&self, frame: &Mat, title: &str) -> Option<ResultsScreen> {
+        // Step 3: the match name is whatever comes before "Results".
+        // The event name may be in front of it, so take the best parse over every starting word.
+        let words = title.split_whitespace().collect::<Vec<&str>>();
+        let end = words.iter().position(|w| utils::edit_distance(&w.to_lowercase(), RESULTS_WORD) <= 2)?;
+        let parsed = (0..end)
+            .filter_map(|start| match_name::parse(&words[start..end].join(" ")).ok())
+            .max_by(|a, b| a.confidence.total_cmp(&b.confidence))?;
+        if parsed.confidence < MIN_TITLE_CONFIDENCE {
+            tracing::debug!("Results title {title:?} parsed as {:?} with low confidence {}", parsed.key, parsed.confidence);
+            return None;
+        }
+
+        // Step 4: read the scores.
+        let scores = self.read_scores(frame);
+        tracing::trace!("Detected scores: {scores:?}");
+
+        Some(ResultsScreen { key: parsed.key, event_code: None, season: None, scores })
+    }
+
+    /// Reads both alliances' scores off a full-screen results screen.
+    ///
+    /// Also used on screens we identified by QR code, so this doesn't look at the title.
+    pub fn read_scores(&self, frame: &Mat) -> Option<ResultsScores> {
+        let left = self.read_alliance(frame, LEFT_PANEL_X)?;
+        let right = self.read_alliance(frame, RIGHT_PANEL_X)?;
+
+        let total_box = utils::relative_extract_roi(
+            frame,
+            None,
+            Point::new(LEFT_PANEL_X, PANEL_Y),
+            Size::new(PANEL_WIDTH, TOTAL_HEIGHT)
+        );
+        let hsv = utils::cvt_color(&total_box, imgproc::COLOR_RGB2HSV);
+        let mut thr = Mat::default();
+        cvcore::in_range(&hsv, &[98_u8, 0_u8, 0_u8], &[108_u8, 255_u8, 255_u8], &mut thr).unwrap();
+        let blue_score = cvcore::count_non_zero(&thr).unwrap() as f64 / (total_box.size().unwrap().area() as f64);
+        tracing::trace!("Results screen flipped confidence score: {blue_score}");
+
+        if blue_score > SCORE_BLUE_THRESHOLD {
+            Some(ResultsScores { red: right, blue: left })
+        } else {
+            Some(ResultsScores { red: left, blue: right })
+        }
+    }
+
+    fn read_alliance(&self, frame: &Mat, panel_x: f64) -> Option<AllianceScore> {
+        let roi = utils::relative_extract_roi(
+            frame,
+            None,
+            Point::new(panel_x, PANEL_Y),
+            Size::new(PANEL_WIDTH, TOTAL_HEIGHT)
+        );
+        let total = self.number_ocr.extract_text(&roi).trim().parse::<u64>().ok()?;
+
+        let roi = utils::relative_extract_roi(
+            frame,
+            None,
+            Point::new(panel_x, PANEL_Y + BREAKDOWN_Y),
+            Size::new(PANEL_WIDTH, BREAKDOWN_HEIGHT)
+        );
+        let breakdown = self.title_ocr.extract_text(&roi);
+        let mut score = AllianceScore { total, auto: None, teleop: None, penalty: None };
+        for line in breakdown.lines() {
+            // rows read like "Autonomous 42", the points come last
+            let mut words = line.split_whitespace();
+            let (Some(label), Some(points)) = (words.next(), words.next_back()) else {
+                continue;
+            };
+            let Ok(points) = points.parse::<u64>() else {
+                continue;
+            };
+            let label = label.to_lowercase();
+            let is = |labels: &[&str]| labels.iter().any(|l| utils::edit_distance(&label, l) <= 2);
+            if is(AUTO_LABELS) {
+                score.auto = Some(points);
+            } else if is(TELEOP_LABELS) {
+                score.teleop = Some(points);
+            } else if is(PENALTY_LABELS) {
+                score.penalty = Some(points);
+            }
+        }
+        Some(score)
+    }
+}
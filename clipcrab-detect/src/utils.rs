@@ -203,4 +203,29 @@ pub fn display_mat(name: &str, mat: &Mat) {
 pub fn imwrite(fname: &str, mat: &Mat) {
     let bgr = cvt_color(mat, imgproc::COLOR_RGB2BGR);
     imgcodecs::imwrite_def(fname, &bgr).unwrap();
-}
\ No newline at end of file
+}
+
+/// Whether a frame is (close to) one flat color, going by the spread of its gray levels.
+pub fn is_blank(mat: &Mat, max_stddev: f64) -> bool {
+    let gray = cvt_color(mat, imgproc::COLOR_RGB2GRAY);
+    let mut mean = cvcore::Scalar::default();
+    let mut stddev = cvcore::Scalar::default();
+    cvcore::mean_std_dev(&gray, &mut mean, &mut stddev, &cvcore::no_array()).unwrap();
+    stddev[0] <= max_stddev
+}
+
+/// Levenshtein distance between two strings, by chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut prev = (0..=b.len()).collect::<Vec<usize>>();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
@@ -0,0 +1,35 @@
+//! Decodes the same timestamps through [`extract_frame`], which sets everything up per frame,
+//! and through [`FFMpegger`], which keeps its decoder and scaler around.
+//!
+//! `cargo run --release -p clipcrab-io --example decode_bench -- <video>`
+extern crate ffmpeg_next as ffmpeg;
+
+use clipcrab_io::seek::{FFMpegger, extract_frame};
+use std::time::Instant;
+
+const FRAMES: usize = 200;
+
+fn main() -> Result<(), anyhow::Error> {
+    let p = std::env::args().nth(1).expect("usage: decode_bench <video>");
+    ffmpeg::init().unwrap();
+
+    let mut ictx = ffmpeg::format::input(&p)?;
+    let step = (ictx.duration() as usize / FRAMES).max(1);
+    let timestamps = (0..ictx.duration()).step_by(step).take(FRAMES).collect::<Vec<i64>>();
+
+    let start = Instant::now();
+    for ts in timestamps.iter() {
+        extract_frame(&mut ictx, *ts)?;
+    }
+    let cold = (Instant::now() - start).as_secs_f64() / timestamps.len() as f64;
+
+    let mut seeker = FFMpegger::new(p.as_ref())?;
+    let start = Instant::now();
+    for ts in timestamps.iter() {
+        seeker.extract_frame(*ts)?;
+    }
+    let warm = (Instant::now() - start).as_secs_f64() / timestamps.len() as f64;
+
+    println!("{} frames: {:.3} ms/frame rebuilding, {:.3} ms/frame persistent", timestamps.len(), cold * 1000.0, warm * 1000.0);
+    Ok(())
+}
@@ -7,15 +7,22 @@
 //! we use a swscale context to ensure the frames are in rgb24; this isn't _strictly_ necessary
 //! (and might be better for perf if opencv does it) but the perf impact relative to the ease of use impact is likely negligible.
 //! 
+//! swscale assumes BT.601 limited range unless told otherwise, which shifts colors on the BT.709 streams we mostly get
+//! and throws off the hue thresholds in the detectors. so we set the matrix and range from each frame's own metadata.
+//! primaries aren't converted: nearly everything is BT.709, which shares sRGB's primaries, and we only warn about the rest.
+//! 
 //! ffmpeg's base unit of time (defined in [`AV_TIME_BASE`]) is one microsecond.
 //! that's what it lists the duration and seek timestep as.
 extern crate ffmpeg_next as ffmpeg;
 
+use ffmpeg::color;
+use ffmpeg::Rescale;
 use ffmpeg::format::{input, Pixel};
 use ffmpeg::media::Type;
 use ffmpeg::software::scaling::{context::Context, flag::Flags};
 use ffmpeg::util::frame::video::Video;
 use ffmpeg_next::ffi::AV_TIME_BASE;
+use opencv::prelude::*;
 use std::path::Path;
 use std::time::Instant;
 
@@ -32,7 +39,7 @@ pub fn seek_test(p: impl AsRef<Path>) -> Result<(), ffmpeg::Error> {
     let mut cnt = 0_u64;
 
     for sec in (0..duration_us).step_by(AV_TIME_BASE as usize * 15) {
-        let frame = extract_frame(&mut ictx, sec)?;
+        let (_, frame) = extract_frame(&mut ictx, sec)?;
         let _ = conv_to_mat(&frame).unwrap();
         force_opt = force_opt.wrapping_add(frame.data(0).len());
 
@@ -47,19 +54,58 @@ pub fn seek_test(p: impl AsRef<Path>) -> Result<(), ffmpeg::Error> {
     Ok(())
 }
 
+/// What a scaler was set up for. If a frame doesn't match, the scaler gets rebuilt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ScalerKey {
+    format: Pixel,
+    width: u32,
+    height: u32,
+    space: color::Space,
+    range: color::Range,
+}
+
+impl ScalerKey {
+    fn of(frame: &Video) -> Self {
+        Self {
+            format: frame.format(),
+            width: frame.width(),
+            height: frame.height(),
+            space: frame.color_space(),
+            range: frame.color_range(),
+        }
+    }
+}
+
+/// Seeks around one input, keeping the decoder and scaler alive between frames.
 pub struct FFMpegger {
     pub ictx: ffmpeg::format::context::Input,
     pub duration_us: i64,
+    video_stream_index: usize,
+    time_base: ffmpeg::Rational,
+    decoder: ffmpeg::decoder::Video,
+    scaler: Option<(ScalerKey, Context)>,
 }
 
 impl FFMpegger {
     pub fn new(p: &Path) -> Result<Self, ffmpeg::Error> {
         let ictx = input(p)?;
         let duration_us = ictx.duration();
+        let stream = ictx
+            .streams()
+            .best(Type::Video)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let video_stream_index = stream.index();
+        let time_base = stream.time_base();
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?.decoder().video()?;
+        warn_primaries(decoder.color_primaries());
         Ok(
             Self {
                 ictx,
                 duration_us,
+                video_stream_index,
+                time_base,
+                decoder,
+                scaler: None,
             }
         )
     }
@@ -68,15 +114,273 @@ impl FFMpegger {
         self.duration_us
     }
 
-    pub fn extract_mat(&mut self, ts: i64) -> Result<opencv::core::Mat, anyhow::Error> {
-        let frame = extract_frame(&mut self.ictx, ts)?;
+    /// Seeks to `ts` and decodes up to the first frame at or after it, as `(frame time, rgb24 frame)`.
+    ///
+    /// The frame time is the frame's own presentation time, which can be a little after `ts`.
+    pub fn extract_frame(&mut self, ts: i64) -> Result<(i64, Video), ffmpeg::Error> {
+        self.ictx.seek(ts, ..ts)?;
+        // whatever the decoder still has buffered is from before the seek
+        self.decoder.flush();
+
+        let time_base = self.time_base;
+        // frames without a time are taken as the one we asked for
+        let time_of = |frame: &Video| frame_time_us(frame, time_base).unwrap_or(ts);
+
+        let mut decoded = Video::empty();
+        for (stream, packet) in self.ictx.packets() {
+            if stream.index() != self.video_stream_index {
+                continue;
+            }
+            self.decoder.send_packet(&packet)?;
+            // one packet can give zero or several frames
+            while self.decoder.receive_frame(&mut decoded).is_ok() {
+                let time = time_of(&decoded);
+                if time >= ts {
+                    return Ok((time, to_rgb(&mut self.scaler, &decoded)?));
+                }
+            }
+        }
+
+        // out of packets, drain what's left. the flush after the next seek takes the decoder out of draining.
+        self.decoder.send_eof()?;
+        let mut last = None;
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            let time = time_of(&decoded);
+            if time >= ts {
+                return Ok((time, to_rgb(&mut self.scaler, &decoded)?));
+            }
+            last = Some((time, decoded.clone()));
+        }
+        // past the last frame, the last frame is as close as we get
+        match last {
+            Some((time, frame)) => Ok((time, to_rgb(&mut self.scaler, &frame)?)),
+            None => Err(ffmpeg::Error::StreamNotFound),
+        }
+    }
+
+    /// Decodes forward from the first of `timestamps` once, instead of seeking for each one.
+    ///
+    /// Much cheaper than [`FFMpegger::extract_frame`] per timestamp when they're closer together than the keyframes are,
+    /// since every seek decodes again from the previous keyframe. Timestamps get sorted.
+    pub fn frames_at(&mut self, mut timestamps: Vec<i64>) -> SequentialFrames<'_> {
+        timestamps.sort();
+        SequentialFrames {
+            seeker: self,
+            timestamps: timestamps.into(),
+            decoded: Video::empty(),
+            last: None,
+            started: false,
+            eof: false,
+        }
+    }
+
+    /// Visits only the keyframes from `start_us` up to `end_us`, as `(frame time, rgb24 frame)`.
+    ///
+    /// Non-key packets never reach the decoder, so this gets through hours of stream far faster than decoding everything,
+    /// at the cost of only getting frames wherever the encoder put its keyframes.
+    pub fn keyframes(&mut self, start_us: i64, end_us: i64) -> KeyFrames<'_> {
+        KeyFrames {
+            seeker: self,
+            start_us,
+            end_us,
+            decoded: Video::empty(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Like [`FFMpegger::extract_frame`], as `(frame time, Mat)`.
+    pub fn extract_mat(&mut self, ts: i64) -> Result<(i64, opencv::core::Mat), anyhow::Error> {
+        let (time, frame) = self.extract_frame(ts)?;
         let mat = conv_to_mat(&frame)?;
-        Ok(mat)
+        Ok((time, mat))
+    }
+
+    /// Like [`FFMpegger::extract_mat`], but reuses `mat`'s buffer if it's already the right size. Returns the frame time.
+    pub fn extract_mat_into(&mut self, ts: i64, mat: &mut opencv::core::Mat) -> Result<i64, anyhow::Error> {
+        let (time, frame) = self.extract_frame(ts)?;
+        conv_into_mat(&frame, mat)?;
+        Ok(time)
+    }
+}
+
+/// Iterator from [`FFMpegger::frames_at`].
+///
+/// Yields `(frame time, rgb24 frame)` per timestamp, the frame being the first at or after the timestamp.
+/// Past the end of the stream, timestamps get the last frame.
+pub struct SequentialFrames<'a> {
+    seeker: &'a mut FFMpegger,
+    timestamps: std::collections::VecDeque<i64>,
+    decoded: Video,
+    /// Last frame decoded, as (frame time, decoded frame). Only converted to rgb24 when it's yielded,
+    /// most frames get decoded past. Several timestamps can land on one frame.
+    last: Option<(Option<i64>, Video)>,
+    started: bool,
+    eof: bool,
+}
+
+impl SequentialFrames<'_> {
+    /// Decodes the next frame into `last`. Returns false once the stream's run out.
+    fn decode_next(&mut self) -> Result<bool, ffmpeg::Error> {
+        let seeker = &mut *self.seeker;
+        loop {
+            if seeker.decoder.receive_frame(&mut self.decoded).is_ok() {
+                let time = frame_time_us(&self.decoded, seeker.time_base);
+                // keep the frame, and decode the next one into the one it replaces
+                let previous = self.last.take().map_or_else(Video::empty, |(_, frame)| frame);
+                self.last = Some((time, std::mem::replace(&mut self.decoded, previous)));
+                return Ok(true);
+            }
+            if self.eof {
+                return Ok(false);
+            }
+            let mut packet = ffmpeg::Packet::empty();
+            match packet.read(&mut seeker.ictx) {
+                Ok(()) if packet.stream() == seeker.video_stream_index => seeker.decoder.send_packet(&packet)?,
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => {
+                    seeker.decoder.send_eof()?;
+                    self.eof = true;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Iterator for SequentialFrames<'_> {
+    type Item = Result<(i64, Video), ffmpeg::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ts = *self.timestamps.front()?;
+        if !self.started {
+            self.started = true;
+            if let Err(e) = self.seeker.ictx.seek(ts, ..ts) {
+                return Some(Err(e));
+            }
+            self.seeker.decoder.flush();
+        }
+
+        // frames without a time can't tell us we've got to `ts`, so decode on past them
+        while self.last.as_ref().is_none_or(|(time, _)| time.is_none_or(|time| time < ts)) {
+            match self.decode_next() {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.timestamps.pop_front();
+        // past the end of the stream, a last frame without a time is taken as the one we asked for
+        let (time, frame) = self.last.as_ref()?;
+        Some(to_rgb(&mut self.seeker.scaler, frame).map(|rgb| (time.unwrap_or(ts), rgb)))
+    }
+}
+
+/// Iterator from [`FFMpegger::keyframes`].
+pub struct KeyFrames<'a> {
+    seeker: &'a mut FFMpegger,
+    start_us: i64,
+    end_us: i64,
+    decoded: Video,
+    started: bool,
+    done: bool,
+}
+
+impl KeyFrames<'_> {
+    fn next_keyframe(&mut self) -> Result<Option<(i64, Video)>, ffmpeg::Error> {
+        let seeker = &mut *self.seeker;
+        if !self.started {
+            self.started = true;
+            seeker.ictx.seek(self.start_us, ..self.start_us)?;
+            seeker.decoder.flush();
+            // belt and braces: we only ever send keyframes, but this stops the decoder working on anything else
+            unsafe {
+                (*seeker.decoder.as_mut_ptr()).skip_frame = ffmpeg::ffi::AVDiscard::AVDISCARD_NONKEY;
+            }
+        }
+
+        let mut packet = ffmpeg::Packet::empty();
+        loop {
+            match packet.read(&mut seeker.ictx) {
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            if packet.stream() != seeker.video_stream_index || !packet.is_key() {
+                continue;
+            }
+
+            // drain after every keyframe, otherwise decoders with a reordering delay hold on to it
+            // until more packets come in, and the only packets coming are the next keyframes
+            seeker.decoder.send_packet(&packet)?;
+            seeker.decoder.send_eof()?;
+            let got_frame = seeker.decoder.receive_frame(&mut self.decoded).is_ok();
+            seeker.decoder.flush();
+            if !got_frame {
+                continue;
+            }
+
+            let Some(time) = frame_time_us(&self.decoded, seeker.time_base) else {
+                continue;
+            };
+            // the seek lands on the keyframe before the start
+            if time < self.start_us {
+                continue;
+            }
+            if time >= self.end_us {
+                return Ok(None);
+            }
+            return Ok(Some((time, to_rgb(&mut seeker.scaler, &self.decoded)?)));
+        }
+    }
+}
+
+impl Iterator for KeyFrames<'_> {
+    type Item = Result<(i64, Video), ffmpeg::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_keyframe().transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+impl Drop for KeyFrames<'_> {
+    fn drop(&mut self) {
+        // the decoder outlives us, put it back to decoding everything
+        unsafe {
+            (*self.seeker.decoder.as_mut_ptr()).skip_frame = ffmpeg::ffi::AVDiscard::AVDISCARD_DEFAULT;
+        }
+        self.seeker.decoder.flush();
+    }
+}
+
+/// Time of a decoded frame in microseconds, if it has one.
+///
+/// Rescaled in integers by ffmpeg: going through an `i32` overflows a few hours in on fine time bases like 1/90000.
+fn frame_time_us(frame: &Video, time_base: ffmpeg::Rational) -> Option<i64> {
+    frame.timestamp().map(|pts| pts.rescale(time_base, ffmpeg::rescale::TIME_BASE))
+}
+
+/// Converts a frame to rgb24, (re)making the scaler if the frame's format, size or color changed.
+fn to_rgb(scaler: &mut Option<(ScalerKey, Context)>, frame: &Video) -> Result<Video, ffmpeg::Error> {
+    let key = ScalerKey::of(frame);
+    if scaler.as_ref().is_none_or(|(current, _)| *current != key) {
+        *scaler = Some((key, rgb_scaler(frame)?));
     }
+    let (_, context) = scaler.as_mut().unwrap();
+    let mut rgb_frame = Video::empty();
+    context.run(frame, &mut rgb_frame)?;
+    Ok(rgb_frame)
 }
 
 /// Extracts a frame from the ts in the input file.
-pub fn extract_frame(ictx: &mut ffmpeg::format::context::Input, ts: i64) -> Result<Video, ffmpeg::Error> {
+///
+/// This sets up a decoder and scaler from scratch every call; use [`FFMpegger`] for anything more than a one-off.
+pub fn extract_frame(ictx: &mut ffmpeg::format::context::Input, ts: i64) -> Result<(i64, Video), ffmpeg::Error> {
     ictx.seek(ts, ..ts)?;
     let input = ictx
         .streams()
@@ -86,42 +390,212 @@ pub fn extract_frame(ictx: &mut ffmpeg::format::context::Input, ts: i64) -> Resu
 
     let context_decoder = ffmpeg::codec::context::Context::from_parameters(input.parameters())?;
     let mut decoder = context_decoder.decoder().video()?;
+    warn_primaries(decoder.color_primaries());
     let time_base = input.time_base();
 
-    // this has the nice side effect of making sure everything is rgb24
-    let mut scaler = Context::get(
-        decoder.format(),
-        decoder.width(),
-        decoder.height(),
-        Pixel::RGB24,
-        decoder.width(),
-        decoder.height(),
-        Flags::FAST_BILINEAR,
-    )?;
-
     let mut decoded = Video::empty();
     for (stream, packet) in ictx.packets() {
         if stream.index() == video_stream_index {
             decoder.send_packet(&packet)?;
             decoder.receive_frame(&mut decoded)?;
-            if decoded.timestamp().map_or(ts, |dts| {
-                f64::from(ffmpeg::Rational::new(dts as i32, 1) / time_base) as i64
-            }) >= ts {
+            let time = frame_time_us(&decoded, time_base).unwrap_or(ts);
+            if time >= ts {
+                let mut scaler = rgb_scaler(&decoded)?;
                 let mut rgb_frame = Video::empty();
                 scaler.run(&decoded, &mut rgb_frame)?;
                 decoder.send_eof()?;
-                return Ok(rgb_frame);
+                return Ok((time, rgb_frame));
             }
         }
     }
     Err(ffmpeg::Error::StreamNotFound)
 }
 
+/// Warns, once per opened stream, about primaries [`rgb_scaler`] doesn't convert.
+fn warn_primaries(primaries: color::Primaries) {
+    if !matches!(primaries, color::Primaries::BT709 | color::Primaries::Unspecified) {
+        tracing::warn!("{primaries:?} primaries are converted as if they were BT.709");
+    }
+}
+
+/// Makes a swscale context that turns frames like `frame` into full-range rgb24, using the frame's own matrix and range.
+pub fn rgb_scaler(frame: &Video) -> Result<Context, ffmpeg::Error> {
+    // accurate rounding and full chroma interpolation, or the edges of colored boxes bleed
+    let mut scaler = Context::get(
+        frame.format(),
+        frame.width(),
+        frame.height(),
+        Pixel::RGB24,
+        frame.width(),
+        frame.height(),
+        Flags::BILINEAR | Flags::ACCURATE_RND | Flags::FULL_CHR_H_INT,
+    )?;
+
+    let matrix = match frame.color_space() {
+        color::Space::BT709 => ffmpeg::ffi::SWS_CS_ITU709,
+        color::Space::FCC => ffmpeg::ffi::SWS_CS_FCC,
+        color::Space::BT470BG => ffmpeg::ffi::SWS_CS_ITU601,
+        color::Space::SMPTE170M => ffmpeg::ffi::SWS_CS_SMPTE170M,
+        color::Space::SMPTE240M => ffmpeg::ffi::SWS_CS_SMPTE240M,
+        color::Space::BT2020NCL | color::Space::BT2020CL => ffmpeg::ffi::SWS_CS_BT2020,
+        // same guess ffmpeg makes for untagged streams: HD is 709, SD is 601
+        _ if frame.height() >= 720 => ffmpeg::ffi::SWS_CS_ITU709,
+        _ => ffmpeg::ffi::SWS_CS_ITU601,
+    };
+    // the deprecated yuvj formats are full range whatever the tag says
+    let full_range = frame.color_range() == color::Range::JPEG
+        || matches!(frame.format(), Pixel::YUVJ420P | Pixel::YUVJ422P | Pixel::YUVJ444P);
+
+    // SAFETY: the context is valid for as long as `scaler` is, and the coefficient tables are static
+    let ret = unsafe {
+        ffmpeg::ffi::sws_setColorspaceDetails(
+            scaler.as_mut_ptr(),
+            ffmpeg::ffi::sws_getCoefficients(matrix as _),
+            full_range as _,
+            ffmpeg::ffi::sws_getCoefficients(ffmpeg::ffi::SWS_CS_DEFAULT as _),
+            1, // rgb is always full range
+            0,
+            1 << 16,
+            1 << 16,
+        )
+    };
+    if ret < 0 {
+        return Err(ffmpeg::Error::from(ret));
+    }
+    Ok(scaler)
+}
+
 /// Converts a video to an opencv mat
 pub fn conv_to_mat(video: &Video) -> Result<opencv::core::Mat, opencv::Error> {
-    opencv::core::Mat::new_rows_cols_with_bytes::<opencv::core::Point3_<u8>>(
-        video.height() as i32,
-        video.width() as i32,
-        video.data(0)
-    ).map(|r| r.clone_pointee())
-}
\ No newline at end of file
+    let mut mat = opencv::core::Mat::default();
+    conv_into_mat(video, &mut mat)?;
+    Ok(mat)
+}
+
+/// Copies an rgb24 frame into `mat`, which is only reallocated if it isn't already the frame's size.
+///
+/// Stride-correct with one copy: ffmpeg pads rows out for alignment, so each row is copied straight
+/// into place rather than assuming `width * 3` byte rows.
+pub fn conv_into_mat(video: &Video, mat: &mut opencv::core::Mat) -> Result<(), opencv::Error> {
+    let (width, height) = (video.width() as i32, video.height() as i32);
+    if mat.rows() != height || mat.cols() != width || mat.typ() != opencv::core::CV_8UC3 || !mat.is_continuous() {
+        *mat = opencv::core::Mat::new_rows_cols_with_default(height, width, opencv::core::CV_8UC3, opencv::core::Scalar::all(0.0))?;
+    }
+
+    let row_bytes = width as usize * 3;
+    let stride = video.stride(0);
+    let out = mat.data_bytes_mut()?;
+    for (dst, src) in out.chunks_exact_mut(row_bytes).zip(video.data(0).chunks(stride)) {
+        dst.copy_from_slice(&src[..row_bytes]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RGB: [u8; 3] = [0x20, 0x60, 0xC0];
+
+    /// Y'CbCr of `rgb` for a matrix with luma weights `kr` and `kb`, limited or full range.
+    fn to_ycbcr(rgb: [u8; 3], kr: f64, kb: f64, full_range: bool) -> [u8; 3] {
+        let [r, g, b] = rgb.map(|c| c as f64 / 255.0);
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let cb = (b - y) / (2.0 * (1.0 - kb));
+        let cr = (r - y) / (2.0 * (1.0 - kr));
+        let (y, c) = if full_range { (255.0 * y, 255.0) } else { (16.0 + 219.0 * y, 224.0) };
+        [y, 128.0 + c * cb, 128.0 + c * cr].map(|v| v.round() as u8)
+    }
+
+    /// A single-color yuv444p frame tagged with `space` and `range`.
+    fn solid_frame(height: u32, yuv: [u8; 3], space: color::Space, range: color::Range) -> Video {
+        let mut frame = Video::new(Pixel::YUV444P, 64, height);
+        for (plane, value) in yuv.into_iter().enumerate() {
+            frame.data_mut(plane).fill(value);
+        }
+        frame.set_color_space(space);
+        frame.set_color_range(range);
+        frame
+    }
+
+    fn assert_decodes_to(frame: &Video, want: [u8; 3]) {
+        const TOLERANCE: i32 = 3;
+        let rgb = to_rgb(&mut None, frame).unwrap();
+        let i = (frame.height() as usize / 2) * rgb.stride(0) + 32 * 3;
+        let got = &rgb.data(0)[i..i + 3];
+        assert!(
+            got.iter().zip(want).all(|(got, want)| (*got as i32 - want as i32).abs() <= TOLERANCE),
+            "{:?} {:?} decoded to {got:?}, expected {want:?}",
+            frame.color_space(),
+            frame.color_range(),
+        );
+    }
+
+    #[test]
+    fn color_limited_range() {
+        ffmpeg::init().unwrap();
+        let frame = solid_frame(16, to_ycbcr(RGB, 0.2126, 0.0722, false), color::Space::BT709, color::Range::MPEG);
+        assert_decodes_to(&frame, RGB);
+    }
+
+    #[test]
+    fn color_full_range() {
+        ffmpeg::init().unwrap();
+        let frame = solid_frame(16, to_ycbcr(RGB, 0.2126, 0.0722, true), color::Space::BT709, color::Range::JPEG);
+        assert_decodes_to(&frame, RGB);
+    }
+
+    #[test]
+    fn color_bt601() {
+        ffmpeg::init().unwrap();
+        let frame = solid_frame(16, to_ycbcr(RGB, 0.299, 0.114, false), color::Space::BT470BG, color::Range::MPEG);
+        assert_decodes_to(&frame, RGB);
+    }
+
+    #[test]
+    fn color_untagged_guesses_by_height() {
+        ffmpeg::init().unwrap();
+        let hd = solid_frame(720, to_ycbcr(RGB, 0.2126, 0.0722, false), color::Space::Unspecified, color::Range::MPEG);
+        assert_decodes_to(&hd, RGB);
+        let sd = solid_frame(480, to_ycbcr(RGB, 0.299, 0.114, false), color::Space::Unspecified, color::Range::MPEG);
+        assert_decodes_to(&sd, RGB);
+    }
+
+    /// Fills an odd-width frame, so its rows get padded, with a known pattern and checks it converts unskewed.
+    fn check_stride(width: u32, height: u32, mat: &mut opencv::core::Mat) {
+        let pattern = |x: usize, y: usize| [(x % 251) as u8, (y % 241) as u8, ((x + y) % 239) as u8];
+        let mut frame = Video::new(Pixel::RGB24, width, height);
+        let stride = frame.stride(0);
+        let data = frame.data_mut(0);
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let i = y * stride + x * 3;
+                data[i..i + 3].copy_from_slice(&pattern(x, y));
+            }
+        }
+
+        conv_into_mat(&frame, mat).unwrap();
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let px = mat.at_2d::<opencv::core::Vec3b>(y as i32, x as i32).unwrap().0;
+                assert_eq!(px, pattern(x, y), "{width}x{height} (stride {stride}): pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn stride_odd_widths() {
+        for (width, height) in [(1, 1), (3, 5), (641, 359), (1279, 719)] {
+            check_stride(width, height, &mut opencv::core::Mat::default());
+        }
+    }
+
+    #[test]
+    fn stride_reused_mat() {
+        // the workers keep one mat around, so it gets resized both ways
+        let mut mat = opencv::core::Mat::default();
+        for (width, height) in [(1279, 719), (3, 5), (641, 359), (1, 1)] {
+            check_stride(width, height, &mut mat);
+        }
+    }
+}
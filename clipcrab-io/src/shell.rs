@@ -73,11 +73,15 @@ pub fn ingest(fname: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<(), a
     Ok(())
 }
 
+/// Cuts `pairs` out of the input and joins them into one clip, e.g. a match followed by its results screen.
+///
+/// Errors if ffmpeg fails. The error comes back as the clip task's result, which stops the run before any manifest is written.
 pub fn clip_segments(
     input_file: &Path,
     output_file: &Path,
     pairs: &[(i64, i64)], // start, duration
-) {
+    title: Option<&str>,
+) -> Result<(), anyhow::Error> {
     let mut ffmpeg_incantation_of_god = new_ffmpeg();
 
     for (start, duration) in pairs {
@@ -91,12 +95,25 @@ pub fn clip_segments(
     }
 
     ffmpeg_incantation_of_god.arg("-filter_complex");
+    // concat wants one video and one audio pad per segment, and its outputs mapped explicitly
     let filter: String = (0..pairs.len())
-        .map(|i| format!("[{i}]"))
-        .chain([format!("concat=n={}:v=1:a=1[out];[out]setpts=PTS-STARTPTS", pairs.len())])
+        .map(|i| format!("[{i}:v:0][{i}:a:0]"))
+        .chain([format!("concat=n={}:v=1:a=1[outv][outa]", pairs.len())])
         .collect();
 
     ffmpeg_incantation_of_god.arg(&filter);
-    ffmpeg_incantation_of_god.args(["-c:a", "libopus", "-b", "96000", "-c:v", "libsvtav1", "-crf", "23"]);
+    ffmpeg_incantation_of_god.args(["-map", "[outv]", "-map", "[outa]"]);
+    if let Some(title) = title {
+        ffmpeg_incantation_of_god.arg("-metadata");
+        ffmpeg_incantation_of_god.arg(format!("title={title}"));
+    }
+    // bitrate is for the audio only, the video is crf
+    ffmpeg_incantation_of_god.args(["-c:a", "libopus", "-b:a", "96000", "-c:v", "libsvtav1", "-crf", "23"]);
     ffmpeg_incantation_of_god.arg(output_file);
+
+    let status = ffmpeg_incantation_of_god.status()?;
+    if !status.success() {
+        anyhow::bail!("ffmpeg exited with {status} while clipping {}", output_file.display());
+    }
+    Ok(())
 }
\ No newline at end of file
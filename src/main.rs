@@ -1,7 +1,8 @@
-use std::{path::PathBuf, time::Duration};
+use std::{path::{Path, PathBuf}, time::Duration};
 
 use clap::Parser;
 
+pub mod manifest;
 pub mod model;
 pub mod worker;
 
@@ -12,6 +13,21 @@ struct Cli {
     workers: u64,
     #[arg(short, long)]
     start_ts: Option<String>,
+    /// Event roster (team list JSON or plain list of team numbers) to check team numbers against
+    #[arg(short, long)]
+    roster: Option<PathBuf>,
+    /// Only clip these matches, by compact key or match name (e.g. `Q12,P3-T2,Playoff Match 4`)
+    #[arg(long, value_delimiter = ',')]
+    only: Vec<clipcrab_detect::MatchKey>,
+    /// Start clips at the match preview (team intro) instead of the match start
+    #[arg(long)]
+    intro: bool,
+    /// Language of the scoring software, for reading match names
+    #[arg(long, value_enum, default_value_t)]
+    locale: clipcrab_detect::locale::Locale,
+    /// Only look at keyframes in the first pass. Much faster, but coarser.
+    #[arg(long)]
+    keyframe_scan: bool,
 }
 
 fn main() {
@@ -22,7 +38,13 @@ fn main() {
     let duration_us = clipcrab_io::shell::video_duration_us(&cli.fname);
     let start = cli.start_ts.and_then(|s| clipcrab_io::time::parse_time(&s)).unwrap_or(0);
 
-    let mut proj = worker::OfflineEventProject::new(start, duration_us);
+    let roster = cli.roster.as_ref().map(|p| clipcrab_detect::roster::Roster::load(p).unwrap());
+    if let Some(roster) = &roster {
+        tracing::info!("Loaded roster with {} teams", roster.len());
+    }
+
+    let mut proj = worker::OfflineEventProject::new(start, duration_us).with_only(cli.only).with_intros(cli.intro).with_locale(cli.locale).with_keyframe_scan(cli.keyframe_scan);
+    std::fs::create_dir_all(&cli.out_dir).unwrap();
 
     let (task_send, task_recv) = crossbeam_channel::unbounded();
     let (result_send, result_recv) = crossbeam_channel::unbounded();
@@ -34,8 +56,10 @@ fn main() {
         let out_dir = cli.out_dir.clone();
         let tasks = task_recv.clone();
         let results = result_send.clone();
-        workers.push(std::thread::spawn(|| {
-            worker(fname, out_dir, tasks, results);
+        let roster = roster.clone();
+        let locale = cli.locale;
+        workers.push(std::thread::spawn(move || {
+            worker(fname, out_dir, roster, locale, tasks, results);
         }));
     }
 
@@ -57,9 +81,24 @@ fn main() {
                         tracing::info!("Waiting on {} tasks...", proj.in_flight().len());
                     }
                 }
+                if proj.done() {
+                    break;
+                }
             }
         }
     }
+
+    for _ in workers.iter() {
+        task_send.send(worker::Task::Done).unwrap();
+    }
+    for w in workers {
+        w.join().unwrap();
+    }
+
+    std::fs::write(
+        cli.out_dir.join("manifest.json"),
+        serde_json::to_string_pretty(proj.manifest()).unwrap()
+    ).unwrap();
     
     //let detector = clipcrab_detect::seasons::s2025_decode::DecodeDetector::new();
     //let frame = opencv::imgcodecs::imread(
@@ -74,17 +113,31 @@ fn main() {
 fn worker(
     fname: PathBuf,
     out_dir: PathBuf,
+    roster: Option<clipcrab_detect::roster::Roster>,
+    locale: clipcrab_detect::locale::Locale,
     tasks: crossbeam_channel::Receiver<worker::Task>,
     results: crossbeam_channel::Sender<worker::TaskSubmission>
 ) {
     let mut seeker = clipcrab_io::seek::FFMpegger::new(&fname).unwrap();
-    let display_det = clipcrab_detect::seasons::s2025_decode::DecodeDetector::new();
+    let mut display_det = clipcrab_detect::seasons::s2025_decode::DecodeDetector::new().with_locale(locale);
+    if let Some(roster) = roster {
+        display_det = display_det.with_roster(roster);
+    }
+    let mut detector = clipcrab_detect::seasons::s2025_decode::DecodeFrameDetector::new(display_det).with_locale(locale);
+    // reused between frames so we aren't allocating a fresh one every time
+    let mut frame = opencv::core::Mat::default();
 
     while let Ok(task) = tasks.recv() {
         tracing::trace!("Processing {:?}", task);
-        let result = match task {
+        let result = match &task {
             worker::Task::AnalyzeFrame(ts) => {
-                analyze_frame(&mut seeker, &display_det, ts)
+                analyze_frame(&mut seeker, &mut detector, &mut frame, *ts)
+            }
+            worker::Task::AnalyzeRun(timestamps) => {
+                analyze_run(&mut seeker, &mut detector, &mut frame, timestamps.clone())
+            }
+            worker::Task::AnalyzeKeyframes(segment) => {
+                analyze_keyframes(&mut seeker, &mut detector, &mut frame, segment.start, segment.start + segment.duration())
             }
             worker::Task::ClipMatch(clip_match_request) => {
                 let mut pairs = vec![];
@@ -93,12 +146,11 @@ fn worker(
                     pairs.push((result_screen.start, result_screen.duration()));
                 }
 
-                clipcrab_io::shell::clip_segments(
-                    &fname,
-                    &out_dir.join(format!("{}.mkv", clip_match_request.key)),
-                    &pairs
-                );
-                worker::TaskResult::ClipDone
+                clip(&fname, &out_dir.join(clip_match_request.path()), &pairs, &clip_match_request.title)
+            }
+            worker::Task::ClipCeremony(request) => {
+                let pairs = [(request.segment.start, request.segment.duration())];
+                clip(&fname, &out_dir.join(request.path()), &pairs, &request.title)
             }
             worker::Task::Done => {
                 return;
@@ -109,22 +161,66 @@ fn worker(
     }
 }
 
+fn clip(fname: &Path, out_file: &Path, pairs: &[(i64, i64)], title: &str) -> worker::TaskResult {
+    if let Some(parent) = out_file.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    match clipcrab_io::shell::clip_segments(fname, out_file, pairs, Some(title)) {
+        Ok(()) => worker::TaskResult::ClipDone,
+        Err(e) => worker::TaskResult::Error(format!("{e}")),
+    }
+}
+
 fn analyze_frame(
     seeker: &mut clipcrab_io::seek::FFMpegger,
-    display_det: &dyn clipcrab_detect::Detector,
+    detector: &mut dyn clipcrab_detect::Detector,
+    frame: &mut opencv::core::Mat,
     ts: i64
 ) -> worker::TaskResult {
-    let frame = match seeker.extract_mat(ts) {
-        Ok(frame) => frame,
-        Err(e) => {
+    match seeker.extract_mat_into(ts, frame) {
+        // the frame's own time, which can be a little after the one asked for
+        Ok(time) => worker::TaskResult::Frame(time, detector.detect(frame)),
+        Err(e) => worker::TaskResult::Error(format!("{e}")),
+    }
+}
+
+fn analyze_run(
+    seeker: &mut clipcrab_io::seek::FFMpegger,
+    detector: &mut dyn clipcrab_detect::Detector,
+    frame: &mut opencv::core::Mat,
+    timestamps: Vec<i64>
+) -> worker::TaskResult {
+    let mut frames = Vec::with_capacity(timestamps.len());
+    for decoded in seeker.frames_at(timestamps) {
+        let (ts, video) = match decoded {
+            Ok(decoded) => decoded,
+            Err(e) => return worker::TaskResult::Error(format!("{e}")),
+        };
+        if let Err(e) = clipcrab_io::seek::conv_into_mat(&video, frame) {
             return worker::TaskResult::Error(format!("{e}"));
         }
-    };
-    if let Some(det) = display_det.detect(&frame) {
-        return worker::TaskResult::MatchDetection(ts, det);
+        frames.push((ts, detector.detect(frame)));
     }
-    if let Some(det) = clipcrab_detect::qr::detect_qr(&frame) {
-        return worker::TaskResult::MatchResultQR(ts, det);
+    worker::TaskResult::Frames(frames)
+}
+
+fn analyze_keyframes(
+    seeker: &mut clipcrab_io::seek::FFMpegger,
+    detector: &mut dyn clipcrab_detect::Detector,
+    frame: &mut opencv::core::Mat,
+    start_us: i64,
+    end_us: i64
+) -> worker::TaskResult {
+    let mut frames = Vec::new();
+    for decoded in seeker.keyframes(start_us, end_us) {
+        let (ts, video) = match decoded {
+            Ok(decoded) => decoded,
+            Err(e) => return worker::TaskResult::Error(format!("{e}")),
+        };
+        if let Err(e) = clipcrab_io::seek::conv_into_mat(&video, frame) {
+            return worker::TaskResult::Error(format!("{e}"));
+        }
+        frames.push((ts, detector.detect(frame)));
     }
-    worker::TaskResult::None
-}
\ No newline at end of file
+    worker::TaskResult::Frames(frames)
+}
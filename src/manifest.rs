@@ -0,0 +1,105 @@
+//! Manifest of everything we clipped, written next to the clips as `manifest.json`.
+use clipcrab_detect::ResultsScores;
+
+use crate::model::{Ceremony, Lineup, MatchAttempt, MatchId, Segment};
+use crate::worker::{ClipCeremonyRequest, ClipMatchRequest};
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub events: Vec<ManifestEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestEvent {
+    /// Event name as shown on the overlay
+    pub name: Option<String>,
+    /// ftc.events event code
+    pub code: Option<String>,
+    /// Subdirectory the event's clips are in
+    pub dir: Option<String>,
+    /// Stretches of the stream showing this event
+    pub segments: Vec<Segment>,
+    pub matches: Vec<ManifestMatch>,
+    /// Alliance selection and awards clips
+    pub ceremonies: Vec<ManifestCeremony>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestMatch {
+    #[serde(flatten)]
+    pub id: MatchId,
+    /// Human-readable match name
+    pub name: String,
+    /// Teams and match name voted across all detects
+    pub lineup: Option<Lineup>,
+    /// Final scores off the results screen
+    pub scores: Option<ResultsScores>,
+    /// One clip per attempt, in video order. The official run is last.
+    pub clips: Vec<ManifestClip>,
+}
+
+impl ManifestMatch {
+    pub fn new(id: MatchId, lineup: Option<Lineup>, scores: Option<ResultsScores>) -> Self {
+        Self {
+            name: id.key.to_string(),
+            id,
+            lineup,
+            scores,
+            clips: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestClip {
+    /// Path relative to the output directory
+    pub file: String,
+    /// Title written into the clip's metadata
+    pub title: String,
+    /// 1-based attempt number
+    pub attempt: usize,
+    /// Whether this attempt was aborted and replayed
+    pub aborted: bool,
+    pub match_segment: Segment,
+    /// Where the match itself starts, which is later than the clip if it starts at the team intro
+    pub match_start: i64,
+    pub result_segment: Option<Segment>,
+    /// Detected match clock pauses
+    pub pauses: Vec<Segment>,
+}
+
+impl ManifestClip {
+    pub fn new(request: &ClipMatchRequest, attempt: &MatchAttempt) -> Self {
+        Self {
+            file: request.path().to_string_lossy().into_owned(),
+            title: request.title.clone(),
+            attempt: request.attempt,
+            aborted: request.aborted,
+            match_segment: request.match_segment,
+            match_start: attempt.start(),
+            result_segment: request.result_segment,
+            pauses: attempt.fit.pauses.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestCeremony {
+    pub ceremony: Ceremony,
+    /// Path relative to the output directory
+    pub file: String,
+    /// Title written into the clip's metadata
+    pub title: String,
+    pub segment: Segment,
+}
+
+impl ManifestCeremony {
+    pub fn new(request: &ClipCeremonyRequest) -> Self {
+        Self {
+            ceremony: request.ceremony,
+            file: request.path().to_string_lossy().into_owned(),
+            title: request.title.clone(),
+            segment: request.segment,
+        }
+    }
+}
@@ -1,9 +1,21 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
-use clipcrab_detect::{MatchDetection, MatchKey};
+use clipcrab_detect::{DisplayLayout, FrameClass, MatchDetection, MatchKey, ResultsScores};
+
+/// A results screen is tied to a match last seen at most this long before it.
+const RESULTS_LINK_WINDOW_US: i64 = 600_000_000;
+
+/// Previews more than this long before a run's start aren't its team intro.
+const INTRO_WINDOW_US: i64 = 180_000_000;
+
+/// Ceremony frames further apart than this are separate stretches. The stream cuts away to the camera a lot.
+const CEREMONY_GAP_US: i64 = 180_000_000;
+/// Video kept on either side of a ceremony's first and last frames.
+const CEREMONY_PAD_US: i64 = 15_000_000;
 
 #[derive(Debug, Clone)]
 pub struct WithTime<T> {
+    /// Presentation time of the frame, which can be a little after the time we asked the decoder for
     pub frame_ts_us: i64,
     pub value: T
 }
@@ -41,7 +53,7 @@ impl<T> PartialEq for WithTime<T> {
 }
 impl<T> Eq for WithTime<T> {}
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Segment {
     pub start: i64,
     pub end: i64,
@@ -66,9 +78,302 @@ impl Segment {
     }
 }
 
+/// Detections within this distance of a start hypothesis are counted as agreeing with it.
+const START_INLIER_TOLERANCE_US: i64 = 1_500_000;
+/// Smallest group of agreeing detections that we trust as a stretch of running clock.
+const MIN_CLOCK_GROUP: usize = 3;
+
+/// Result of fitting the match clock against video time for one run of a match.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StartFit {
+    /// Video time at which the clock started
+    pub start: i64,
+    /// Video time of the last detect in this run
+    pub last_seen: i64,
+    /// Detections that agree with the fit
+    pub inliers: usize,
+    /// Detections rejected as outliers (OCR misreads, other runs of the match)
+    pub outliers: usize,
+    /// RMS of the inlier residuals
+    pub residual_rms_us: f64,
+    /// Largest absolute inlier residual
+    pub residual_max_us: i64,
+    /// Video segments during which the match clock was stopped
+    pub pauses: Vec<Segment>,
+}
+
+impl StartFit {
+    fn from_groups(run: &[ClockGroup], total: usize) -> Self {
+        let residuals = run
+            .iter()
+            .flat_map(|g| g.points.iter().map(|p| p.implied_start() - g.start))
+            .collect::<Vec<i64>>();
+        let inliers = residuals.len();
+        let residual_rms_us = (residuals.iter().map(|r| (*r as f64).powi(2)).sum::<f64>() / inliers as f64).sqrt();
+
+        Self {
+            start: run[0].start,
+            last_seen: run.last().unwrap().last().video_ts_us,
+            inliers,
+            outliers: total - inliers,
+            residual_rms_us,
+            residual_max_us: residuals.iter().map(|r| r.abs()).max().unwrap_or(0),
+            pauses: run
+                .windows(2)
+                .map(|w| Segment::from_start_duration(w[0].last().video_ts_us, w[1].start - w[0].start))
+                .collect(),
+        }
+    }
+}
+
+/// A during-match detection as a (video time, elapsed match clock) pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClockPoint {
+    video_ts_us: i64,
+    elapsed_us: i64,
+}
+
+impl ClockPoint {
+    fn from_detection(det: &WithTime<MatchDetection>) -> Option<Self> {
+        let elapsed = match det.phase {
+            clipcrab_detect::MatchPhase::Autonomous => 150 - det.time,
+            clipcrab_detect::MatchPhase::Transition => 38 - det.time,
+            clipcrab_detect::MatchPhase::Teleop => 158 - det.time,
+            clipcrab_detect::MatchPhase::Preview |
+            clipcrab_detect::MatchPhase::NotStarted |
+            clipcrab_detect::MatchPhase::Ended => return None,
+        };
+        Some(Self { video_ts_us: det.frame_ts_us, elapsed_us: elapsed * 1_000_000 })
+    }
+
+    /// Match start implied by this point, as the clock runs in real time.
+    fn implied_start(&self) -> i64 {
+        self.video_ts_us - self.elapsed_us
+    }
+}
+
+/// Points that all agree on one start, sorted by video time.
+#[derive(Debug, Clone)]
+struct ClockGroup {
+    start: i64,
+    points: Vec<ClockPoint>,
+}
+
+impl ClockGroup {
+    fn first(&self) -> &ClockPoint {
+        &self.points[0]
+    }
+
+    fn last(&self) -> &ClockPoint {
+        self.points.last().unwrap()
+    }
+
+    /// Whether `next` continues this group's clock after a pause, rather than restarting it.
+    fn continued_by(&self, next: &ClockGroup) -> bool {
+        next.start > self.start
+            && next.first().video_ts_us >= self.last().video_ts_us - START_INLIER_TOLERANCE_US
+            && next.first().elapsed_us >= self.last().elapsed_us - START_INLIER_TOLERANCE_US
+    }
+}
+
+/// Finds the start with the most agreeing points.
+///
+/// This is RANSAC with the slope pinned to 1, so every point is a complete hypothesis and we can just try all of them.
+/// The winner is refined to the least-squares start of its inliers. Ties go to the later start.
+fn ransac_start(points: &[ClockPoint]) -> Option<(i64, Vec<usize>)> {
+    let inliers_of = |start: i64| {
+        points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| (p.implied_start() - start).abs() <= START_INLIER_TOLERANCE_US)
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>()
+    };
+
+    let (_, inliers) = points
+        .iter()
+        .map(|p| (p.implied_start(), inliers_of(p.implied_start())))
+        .max_by_key(|(start, inliers)| (inliers.len(), *start))?;
+
+    let start = inliers.iter().map(|i| points[*i].implied_start()).sum::<i64>() / inliers.len() as i64;
+    let refined = inliers_of(start);
+    if refined.is_empty() {
+        return Some((start, inliers));
+    }
+    let start = refined.iter().map(|i| points[*i].implied_start()).sum::<i64>() / refined.len() as i64;
+    Some((start, refined))
+}
+
+/// Repeatedly peels off the largest group of agreeing points until only outliers remain.
+fn clock_groups(mut points: Vec<ClockPoint>) -> Vec<ClockGroup> {
+    let mut groups = Vec::new();
+    while let Some((start, inliers)) = ransac_start(&points) {
+        if inliers.len() < MIN_CLOCK_GROUP && !groups.is_empty() {
+            break;
+        }
+        let mut group_points = Vec::with_capacity(inliers.len());
+        for i in inliers.into_iter().rev() {
+            group_points.push(points.swap_remove(i));
+        }
+        group_points.sort_by_key(|p| p.video_ts_us);
+        groups.push(ClockGroup { start, points: group_points });
+
+        if groups.len() == 1 && groups[0].points.len() < MIN_CLOCK_GROUP {
+            // too few detects to tell anything apart, take what we have
+            break;
+        }
+    }
+    groups
+}
+
+/// One run of a match on the field.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MatchAttempt {
+    /// Clock fit for this run
+    pub fit: StartFit,
+    /// Whether this run was cut short and replayed later
+    pub aborted: bool,
+    /// First preview or pre-start frame leading into this run, if any
+    pub intro_start: Option<i64>,
+}
+
+impl MatchAttempt {
+    pub fn start(&self) -> i64 {
+        self.fit.start
+    }
+
+    /// Total time the match clock spent paused during this run.
+    pub fn paused_us(&self) -> i64 {
+        self.fit.pauses.iter().map(Segment::duration).sum()
+    }
+}
+
+/// Match name and teams agreed on across every detect of a match.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Lineup {
+    /// Most common match name as displayed
+    pub name: String,
+    #[serde(default)]
+    pub layout: DisplayLayout,
+    /// On remote events, the one team playing
+    pub red_alliance: Vec<u64>,
+    /// Empty on remote events
+    pub blue_alliance: Vec<u64>,
+    /// Vote share of the least agreed-upon field, 0.0..=1.0
+    pub confidence: f64,
+}
+
+impl Lineup {
+    /// Votes on the name and on each alliance slot separately. Unreadable (`0`) and off-roster teams don't get a vote.
+    pub fn vote<'a>(detections: impl Iterator<Item = &'a MatchDetection> + Clone) -> Option<Self> {
+        let (name, name_confidence) = majority(detections.clone().map(|d| d.name.trim().to_string()))?;
+        let (layout, layout_confidence) = majority(detections.clone().map(|d| d.display_info.layout))?;
+        // only count detects that agree on the layout, or a stray remote read would throw off the alliance sizes
+        let detections = detections.filter(move |d| d.display_info.layout == layout);
+        let (red_alliance, red_confidence) = vote_alliance(detections.clone().map(|d| resolved_teams(d, &d.display_info.red_alliance)));
+        let (blue_alliance, blue_confidence) = vote_alliance(detections.map(|d| resolved_teams(d, &d.display_info.blue_alliance)));
+
+        Some(Self {
+            name,
+            layout,
+            red_alliance,
+            blue_alliance,
+            confidence: name_confidence.min(layout_confidence).min(red_confidence).min(blue_confidence),
+        })
+    }
+
+    /// e.g. `1234 5678 vs 9012 3456`, or just `1234` on remote events
+    pub fn teams_string(&self) -> String {
+        let join = |teams: &[u64]| teams.iter().map(u64::to_string).collect::<Vec<_>>().join(" ");
+        match self.layout {
+            DisplayLayout::Remote => join(&self.red_alliance),
+            DisplayLayout::Traditional => format!("{} vs {}", join(&self.red_alliance), join(&self.blue_alliance)),
+        }
+    }
+}
+
+/// Blanks out teams that didn't resolve against the roster so they don't get a vote.
+fn resolved_teams(detection: &MatchDetection, teams: &[u64]) -> Vec<u64> {
+    teams
+        .iter()
+        .map(|t| if detection.display_info.unresolved_teams.contains(t) { 0 } else { *t })
+        .collect()
+}
+
+/// Majority vote. Returns the winner and its share of the votes. Ties go to the largest value.
+fn majority<T: Ord>(votes: impl Iterator<Item = T>) -> Option<(T, f64)> {
+    let mut counts = BTreeMap::new();
+    let mut total = 0_usize;
+    for v in votes {
+        *counts.entry(v).or_insert(0_usize) += 1;
+        total += 1;
+    }
+    let (winner, n) = counts.into_iter().max_by_key(|(_, n)| *n)?;
+    Some((winner, n as f64 / total as f64))
+}
+
+fn vote_alliance(alliances: impl Iterator<Item = Vec<u64>> + Clone) -> (Vec<u64>, f64) {
+    let Some((len, mut confidence)) = majority(alliances.clone().map(|a| a.len())) else {
+        return (Vec::new(), 0.0);
+    };
+    let teams = (0..len)
+        .map(|slot| {
+            let votes = alliances.clone().filter_map(|a| a.get(slot).copied()).filter(|t| *t != 0);
+            match majority(votes) {
+                Some((team, share)) => {
+                    confidence = confidence.min(share);
+                    team
+                }
+                None => {
+                    confidence = 0.0;
+                    0
+                }
+            }
+        })
+        .collect();
+    (teams, confidence)
+}
+
+/// Identifies a match within a stream.
+///
+/// At championships several divisions each run their own "Qualification 12", so the key alone isn't enough.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+pub struct MatchId {
+    /// ftc.events event code, learned from the results screen QR code
+    pub event_code: Option<String>,
+    /// Division or field, from the OCR'd event name
+    pub division: Option<String>,
+    pub key: MatchKey,
+}
+
+impl MatchId {
+    /// Short form for file names, e.g. `Q12` or `Edison-Q12`.
+    /// The event code is left out since each event gets its own directory.
+    pub fn compact(&self) -> String {
+        match &self.division {
+            Some(division) => format!("{}-{}", division.split_whitespace().collect::<String>(), self.key.compact()),
+            None => self.key.compact(),
+        }
+    }
+}
+
+impl core::fmt::Display for MatchId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(event_code) = &self.event_code {
+            write!(f, "{event_code} ")?;
+        }
+        if let Some(division) = &self.division {
+            write!(f, "{division} ")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
 #[derive(Debug)]
 pub struct Match {
-    pub key: MatchKey,
+    pub id: MatchId,
+    /// Match preview (team intro) detects
+    pub preview_detects: BTreeSet<WithTime<MatchDetection>>,
     /// Pre-match detects
     pub before_detects: BTreeSet<WithTime<MatchDetection>>,
     /// During-match detects
@@ -77,8 +382,14 @@ pub struct Match {
     pub after_detects: BTreeSet<WithTime<MatchDetection>>,
     /// Result screen detects
     pub result_screen_detects: BTreeSet<i64>,
-    /// Determined match start
-    pub start: Option<i64>,
+    /// Scores read off each results screen frame
+    pub result_screen_scores: Vec<ResultsScores>,
+    /// Determined runs of the match, in video order. Only the last one can be official.
+    pub attempts: Vec<MatchAttempt>,
+    /// Voted match name and teams
+    pub lineup: Option<Lineup>,
+    /// Voted final scores
+    pub scores: Option<ResultsScores>,
     /// Earliest results screen
     pub result_screen_earliest: Option<i64>,
     /// Latest results screen
@@ -86,14 +397,18 @@ pub struct Match {
 }
 
 impl Match {
-    pub fn new(key: MatchKey) -> Self {
+    pub fn new(id: MatchId) -> Self {
         Self {
-            key,
+            id,
+            preview_detects: BTreeSet::new(),
             before_detects: BTreeSet::new(),
             during_detects: BTreeSet::new(),
             after_detects: BTreeSet::new(),
             result_screen_detects: BTreeSet::new(),
-            start: None,
+            result_screen_scores: Vec::new(),
+            attempts: Vec::new(),
+            lineup: None,
+            scores: None,
             result_screen_earliest: None,
             result_screen_latest: None,
         }
@@ -101,6 +416,9 @@ impl Match {
 
     pub fn add_detection(&mut self, detection: WithTime<MatchDetection>) {
         match detection.phase {
+            clipcrab_detect::MatchPhase::Preview => {
+                self.preview_detects.insert(detection);
+            }
             clipcrab_detect::MatchPhase::NotStarted => {
                 self.before_detects.insert(detection);
             }
@@ -115,52 +433,157 @@ impl Match {
         }
     }
 
-    pub fn add_results_screen(&mut self, time_us: i64) {
+    pub fn add_results_screen(&mut self, time_us: i64, scores: Option<ResultsScores>) {
         self.result_screen_detects.insert(time_us);
+        self.result_screen_scores.extend(scores);
     }
 
-    pub fn calc_start(&mut self) {
-        let mut est_starts = self.during_detects.iter().map(|det| {
-            det.frame_ts_us - 1_000_000 * match det.phase {
-                clipcrab_detect::MatchPhase::Autonomous => 150 - det.time,
-                clipcrab_detect::MatchPhase::Transition => 38 - det.time,
-                clipcrab_detect::MatchPhase::Teleop => 158 - det.time,
-                clipcrab_detect::MatchPhase::NotStarted |
-                clipcrab_detect::MatchPhase::Ended => panic!("these branches should not be reached!!!"),
-            }
-        }).collect::<Vec<i64>>();
-        est_starts.sort();
-        if est_starts.is_empty() {
+    /// Folds another match's detects into this one.
+    pub fn merge(&mut self, other: Match) {
+        self.preview_detects.extend(other.preview_detects);
+        self.before_detects.extend(other.before_detects);
+        self.during_detects.extend(other.during_detects);
+        self.after_detects.extend(other.after_detects);
+        self.result_screen_detects.extend(other.result_screen_detects);
+        self.result_screen_scores.extend(other.result_screen_scores);
+    }
+
+    /// Video time of the first overlay detect.
+    pub fn first_seen(&self) -> Option<i64> {
+        [&self.preview_detects, &self.before_detects, &self.during_detects, &self.after_detects]
+            .into_iter()
+            .filter_map(|d| d.first().map(|d| d.frame_ts_us))
+            .min()
+    }
+
+    /// Video time of the last overlay detect.
+    pub fn last_seen(&self) -> Option<i64> {
+        [&self.preview_detects, &self.before_detects, &self.during_detects, &self.after_detects]
+            .into_iter()
+            .filter_map(|d| d.last().map(|d| d.frame_ts_us))
+            .max()
+    }
+
+    /// Fits the match clock against video time and works out which runs of the match happened.
+    ///
+    /// If the match was replayed, every run but the last is marked aborted.
+    /// Runs with fewer than 5 detects are dropped as noise whenever a bigger run exists.
+    pub fn calc_attempts(&mut self) {
+        let mut runs = self.fit_runs();
+        if runs.is_empty() {
             tracing::warn!("Match {self:?} has no during-match detects!");
             return;
         }
 
-        if (est_starts.last().unwrap() - est_starts.first().unwrap()).abs() < 10_000_000 {
-            // no replay detected, pick the median
-            self.start = Some(est_starts[est_starts.len() / 2]);
+        if runs.iter().all(|r| r.inliers < 5) {
+            // can't tell a replay from noise here, keep the latest run like we always have
+            let latest = runs.into_iter().max_by_key(|r| r.start).unwrap();
+            runs = vec![latest];
         } else {
-            // we need to do some clustering
-            // we group everything into 5-second bins
-            let clusters = cluster_times(
-                est_starts.iter(),
-                |start, cluster| (start - cluster[0]).abs() > 5_000_000
+            runs.retain(|r| r.inliers >= 5);
+        }
+        if runs.len() > 1 {
+            tracing::warn!(
+                "Replay detected in `{}`, run starts {:?}",
+                self.id,
+                runs.iter().map(|r| r.start).collect::<Vec<_>>()
             );
-            tracing::warn!("Possible replay detected in `{}` clusters {:?}", self.key, clusters);
-
-            self.start = if clusters.iter().all(|v| v.len() < 5) {
-                // if the clusters each have less than 5 detects, pick the one with the biggest median start
-                clusters
-                    .iter()
-                    .map(|c| c[c.len()/2])
-                    .max()
-            } else {
-                // otherwise take groups with >= 5 detects and pick the later one (the replay)
-                clusters
-                    .iter()
-                    .filter_map(|c| (c.len() >= 5).then(|| c[c.len()/2]))
-                    .max()
-            };
         }
+
+        let official = runs.len() - 1;
+        let mut prev_last_seen = i64::MIN;
+        self.attempts = runs
+            .into_iter()
+            .enumerate()
+            .map(|(i, fit)| {
+                let intro_start = self.intro_start(prev_last_seen, fit.start);
+                prev_last_seen = fit.last_seen;
+                for pause in fit.pauses.iter() {
+                    tracing::warn!("Match clock for `{}` paused for {} us at {}", self.id, pause.duration(), pause.start);
+                }
+                tracing::debug!(
+                    "Start fit for `{}`: {} inliers, {} outliers, rms residual {:.0} us",
+                    self.id, fit.inliers, fit.outliers, fit.residual_rms_us
+                );
+                MatchAttempt { fit, aborted: i != official, intro_start }
+            })
+            .collect();
+    }
+
+    /// Earliest preview or pre-start frame after `after` and shortly before `start`.
+    fn intro_start(&self, after: i64, start: i64) -> Option<i64> {
+        self.preview_detects
+            .iter()
+            .chain(self.before_detects.iter())
+            .map(|d| d.frame_ts_us)
+            .filter(|ts| *ts > after && *ts < start && start - ts <= INTRO_WINDOW_US)
+            .min()
+    }
+
+    pub fn calc_lineup(&mut self) {
+        let detects = self.preview_detects
+            .iter()
+            .chain(self.before_detects.iter())
+            .chain(self.during_detects.iter())
+            .chain(self.after_detects.iter())
+            .map(|d| &d.value);
+        self.lineup = Lineup::vote(detects);
+        if let Some(lineup) = &self.lineup {
+            tracing::debug!("Lineup for `{}`: {lineup:?}", self.id);
+        }
+    }
+
+    /// Votes on the final scores across every results screen frame we could read them from.
+    pub fn calc_scores(&mut self) {
+        self.scores = majority(self.result_screen_scores.iter().copied()).map(|(scores, share)| {
+            tracing::debug!("Scores for `{}`: {scores:?} ({:.0}% of reads)", self.id, share * 100.0);
+            scores
+        });
+    }
+
+    /// The attempt that counts, if any.
+    pub fn official_attempt(&self) -> Option<&MatchAttempt> {
+        self.attempts.last().filter(|a| !a.aborted)
+    }
+
+    /// Splits the during-match detects into runs of the match clock and fits a start to each.
+    ///
+    /// Runs are returned in video order.
+    pub fn fit_runs(&self) -> Vec<StartFit> {
+        let points = self.during_detects
+            .iter()
+            .filter_map(ClockPoint::from_detection)
+            .collect::<Vec<ClockPoint>>();
+        let total = points.len();
+
+        let mut groups = clock_groups(points);
+        groups.sort_by_key(|g| g.first().video_ts_us);
+
+        // a group that picks up where an earlier one left off, but with a later implied start,
+        // is the same run with the clock paused in between.
+        let mut runs: Vec<Vec<ClockGroup>> = Vec::new();
+        for group in groups {
+            match runs.iter_mut().rev().find(|run| run.last().unwrap().continued_by(&group)) {
+                Some(run) => run.push(group),
+                None => runs.push(vec![group]),
+            }
+        }
+        runs.sort_by_key(|run| run[0].first().video_ts_us);
+
+        runs.iter().map(|run| StartFit::from_groups(run, total)).collect()
+    }
+
+    /// The 0:00 hold after the official run, from the first stretch of ended-match frames after it.
+    pub fn calc_end_hold(&self) -> Option<Segment> {
+        let start = self.official_attempt()?.start();
+        let clusters = cluster_times(
+            self.after_detects.iter().map(|d| &d.frame_ts_us).filter(|ts| **ts > start),
+            |value, cluster| {
+                (value - cluster.last().unwrap()).abs() > 5_000_000
+            }
+        );
+        let hold = clusters.first()?;
+        Some(Segment::from_start_end(hold[0], *hold.last().unwrap() + 1_000_000))
     }
 
     pub fn calc_result_screen(&self) -> Option<Segment> {
@@ -186,6 +609,165 @@ impl Match {
     }
 }
 
+/// Parts of an event that aren't matches but get their own clip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ceremony {
+    AllianceSelection,
+    Awards,
+}
+
+impl Ceremony {
+    pub fn from_frame(class: &FrameClass) -> Option<Self> {
+        match class {
+            FrameClass::AllianceSelection => Some(Self::AllianceSelection),
+            FrameClass::Awards => Some(Self::Awards),
+            _ => None,
+        }
+    }
+
+    /// File name stem, e.g. `alliance_selection`
+    pub fn stem(&self) -> &'static str {
+        match self {
+            Self::AllianceSelection => "alliance_selection",
+            Self::Awards => "awards",
+        }
+    }
+}
+
+impl std::fmt::Display for Ceremony {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AllianceSelection => write!(f, "Alliance Selection"),
+            Self::Awards => write!(f, "Awards"),
+        }
+    }
+}
+
+/// One event's worth of matches within a stream.
+///
+/// League meets and scrimmages often run several events back to back on one stream.
+#[derive(Debug, Default)]
+pub struct Event {
+    /// Event name as shown on the overlay, if we ever read one
+    pub name: Option<String>,
+    /// ftc.events event code, learned from results screens
+    pub code: Option<String>,
+    /// Video times at which this event's overlay was seen
+    pub sightings: BTreeSet<i64>,
+    pub matches: BTreeMap<MatchId, Match>,
+    /// Video times of alliance selection and awards frames
+    pub ceremonies: BTreeMap<Ceremony, BTreeSet<i64>>,
+}
+
+impl Event {
+    pub fn new(name: Option<String>) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+
+    /// Subdirectory of the output directory this event's clips go into.
+    pub fn dir_name(&self) -> Option<String> {
+        let name = self.code.as_ref().or(self.name.as_ref())?;
+        Some(name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == ' ' { c } else { '_' }).collect())
+    }
+
+    pub fn add_detection(&mut self, key: MatchKey, detection: WithTime<MatchDetection>) {
+        let id = MatchId {
+            event_code: self.code.clone(),
+            division: self.name.as_deref().and_then(clipcrab_detect::match_name::division),
+            key,
+        };
+        self.sightings.insert(detection.frame_ts_us);
+        self.matches.entry(id.clone()).or_insert_with(|| Match::new(id)).add_detection(detection);
+    }
+
+    pub fn add_ceremony(&mut self, ceremony: Ceremony, time_us: i64) {
+        self.ceremonies.entry(ceremony).or_default().insert(time_us);
+    }
+
+    /// The longest stretch of a ceremony, padded a bit on either side.
+    pub fn calc_ceremony(&self, ceremony: Ceremony) -> Option<Segment> {
+        let clusters = cluster_times(
+            self.ceremonies.get(&ceremony)?.iter(),
+            |value, cluster| {
+                (value - cluster.last().unwrap()).abs() > CEREMONY_GAP_US
+            }
+        );
+        let longest = clusters.iter().max_by_key(|c| c.last().unwrap() - c[0])?;
+        Some(Segment::from_start_end(
+            (longest[0] - CEREMONY_PAD_US).max(0),
+            *longest.last().unwrap() + CEREMONY_PAD_US,
+        ))
+    }
+
+    /// Ties a results screen to the nearest match with its key that was on screen before it.
+    pub fn add_results_screen(&mut self, key: MatchKey, time_us: i64, scores: Option<ResultsScores>) {
+        let id = self.matches
+            .iter()
+            .filter(|(id, _)| id.key == key)
+            .filter_map(|(id, m)| Some((id, m.first_seen()?, m.last_seen()?)))
+            .filter(|(_, first, last)| *first <= time_us && time_us - last <= RESULTS_LINK_WINDOW_US)
+            .min_by_key(|(_, _, last)| (time_us - last).abs())
+            .map(|(id, _, _)| id.clone())
+            .unwrap_or_else(|| MatchId { event_code: self.code.clone(), division: None, key });
+        self.matches.entry(id.clone()).or_insert_with(|| Match::new(id)).add_results_screen(time_us, scores);
+    }
+
+    /// Records the event code and re-keys every match with it.
+    pub fn set_code(&mut self, code: &str) {
+        tracing::info!("Event {:?} has code {code}", self.name);
+        self.code = Some(code.to_string());
+        for (old_id, mut m) in std::mem::take(&mut self.matches) {
+            let id = MatchId { event_code: self.code.clone(), ..old_id };
+            m.id = id.clone();
+            match self.matches.get_mut(&id) {
+                Some(existing) => existing.merge(m),
+                None => {
+                    self.matches.insert(id, m);
+                }
+            }
+        }
+    }
+}
+
+/// Splits the stream into stretches where one event's overlay was on screen.
+/// Returns (segment, event index) pairs in video order.
+pub fn event_timeline(events: &[Event]) -> Vec<(Segment, usize)> {
+    let mut sightings = events
+        .iter()
+        .enumerate()
+        .flat_map(|(i, e)| e.sightings.iter().map(move |t| (*t, i)))
+        .collect::<Vec<(i64, usize)>>();
+    sightings.sort();
+
+    let mut timeline: Vec<(Segment, usize)> = Vec::new();
+    for (t, i) in sightings {
+        if matches!(timeline.last(), Some((_, last)) if *last == i) {
+            timeline.last_mut().unwrap().0.end = t;
+        } else {
+            timeline.push((Segment::from_start_end(t, t), i));
+        }
+    }
+    timeline
+}
+
+/// Which event was on screen at `time_us`, going by the nearest stretch of the timeline.
+pub fn event_at(timeline: &[(Segment, usize)], time_us: i64) -> Option<usize> {
+    timeline
+        .iter()
+        .min_by_key(|(segment, _)| {
+            if time_us < segment.start {
+                segment.start - time_us
+            } else {
+                (time_us - segment.end).max(0)
+            }
+        })
+        .map(|(_, i)| *i)
+}
+
 fn cluster_times<'a>(
     times: impl Iterator<Item = &'a i64>,
     mut sep_criteria: impl FnMut(i64, &Vec<i64>) -> bool
@@ -212,4 +794,214 @@ fn cluster_times<'a>(
     }
 
     clusters
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const S: i64 = 1_000_000;
+
+    /// Point seen `video_s` into the video with `elapsed_s` of match clock gone.
+    fn point(video_s: i64, elapsed_s: i64) -> ClockPoint {
+        ClockPoint { video_ts_us: video_s * S, elapsed_us: elapsed_s * S }
+    }
+
+    /// A detect every 5 seconds of a match starting `start_s` into the video.
+    fn run(start_s: i64, elapsed_s: std::ops::Range<i64>) -> Vec<ClockPoint> {
+        elapsed_s.step_by(5).map(|e| point(start_s + e, e)).collect()
+    }
+
+    #[test]
+    fn ransac_clean_run() {
+        let points = run(100, 0..150);
+        let (start, inliers) = ransac_start(&points).unwrap();
+        assert_eq!(start, 100 * S);
+        assert_eq!(inliers.len(), points.len());
+
+        let groups = clock_groups(points.clone());
+        assert_eq!(groups.len(), 1);
+        let fit = StartFit::from_groups(&groups, points.len());
+        assert_eq!(fit.start, 100 * S);
+        assert_eq!(fit.last_seen, 245 * S);
+        assert_eq!(fit.outliers, 0);
+        assert_eq!(fit.residual_max_us, 0);
+        assert!(fit.pauses.is_empty());
+    }
+
+    #[test]
+    fn ransac_rejects_outliers() {
+        let mut points = run(100, 0..150);
+        // misread clocks: 2:30 read as 0:30, and a stray digit
+        points.push(point(110, 130));
+        points.push(point(200, 20));
+        let (start, inliers) = ransac_start(&points).unwrap();
+        assert_eq!(start, 100 * S);
+        assert_eq!(inliers.len(), points.len() - 2);
+
+        let groups = clock_groups(points.clone());
+        let fit = StartFit::from_groups(&groups[..1], points.len());
+        assert_eq!(fit.start, 100 * S);
+        assert_eq!(fit.outliers, 2);
+    }
+
+    #[test]
+    fn ransac_tolerates_jitter() {
+        // detects land anywhere within the second the clock shows
+        let points = (0..150).step_by(5).map(|e| ClockPoint {
+            video_ts_us: (100 + e) * S + (e % 3) * 300_000,
+            elapsed_us: e * S,
+        }).collect::<Vec<ClockPoint>>();
+        let (start, inliers) = ransac_start(&points).unwrap();
+        assert!((start - 100 * S).abs() < S);
+        assert_eq!(inliers.len(), points.len());
+    }
+
+    #[test]
+    fn clock_groups_paused_clock() {
+        // clock stops at 1:00 elapsed for 30 seconds, then picks back up where it left off
+        let mut points = run(100, 0..60);
+        points.extend(run(130, 60..150));
+        let mut groups = clock_groups(points.clone());
+        groups.sort_by_key(|g| g.start);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].start, 100 * S);
+        assert_eq!(groups[1].start, 130 * S);
+        assert!(groups[0].continued_by(&groups[1]));
+
+        let fit = StartFit::from_groups(&groups, points.len());
+        assert_eq!(fit.start, 100 * S);
+        assert_eq!(fit.pauses, vec![Segment::from_start_duration(155 * S, 30 * S)]);
+    }
+
+    #[test]
+    fn clock_groups_replay_is_not_a_pause() {
+        // aborted at 0:40 elapsed, replayed from the start 5 minutes later
+        let mut points = run(100, 0..40);
+        points.extend(run(400, 0..150));
+        let mut groups = clock_groups(points);
+        groups.sort_by_key(|g| g.start);
+        assert_eq!(groups.len(), 2);
+        assert!(!groups[0].continued_by(&groups[1]));
+    }
+
+    #[test]
+    fn clock_groups_too_few_points() {
+        assert!(ransac_start(&[]).is_none());
+        assert!(clock_groups(Vec::new()).is_empty());
+
+        let groups = clock_groups(vec![point(110, 10), point(120, 20)]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].start, 100 * S);
+        assert_eq!(groups[0].points.len(), 2);
+    }
+
+    fn detection(name: &str, layout: DisplayLayout, red: &[u64], blue: &[u64]) -> MatchDetection {
+        MatchDetection {
+            name: name.to_string(),
+            event_name: None,
+            time: 100,
+            phase: clipcrab_detect::MatchPhase::Teleop,
+            display_info: clipcrab_detect::MatchDisplayInfo {
+                layout,
+                red_alliance: red.to_vec(),
+                blue_alliance: blue.to_vec(),
+                display_flipped: false,
+                unresolved_teams: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn majority_picks_most_common() {
+        assert_eq!(majority([3, 1, 3, 2].into_iter()), Some((3, 0.5)));
+        assert_eq!(majority(std::iter::empty::<u64>()), None);
+    }
+
+    #[test]
+    fn majority_tie_goes_to_largest() {
+        assert_eq!(majority([1234, 1243].into_iter()), Some((1243, 0.5)));
+        assert_eq!(majority([1243, 1234].into_iter()), Some((1243, 0.5)));
+    }
+
+    #[test]
+    fn vote_alliance_per_slot() {
+        let reads = vec![vec![1234, 5678], vec![1234, 5678], vec![1284, 5678], vec![1234, 5670]];
+        let (teams, confidence) = vote_alliance(reads.into_iter());
+        assert_eq!(teams, vec![1234, 5678]);
+        assert_eq!(confidence, 0.75);
+    }
+
+    #[test]
+    fn vote_alliance_missing_team() {
+        // unreadable reads are 0 and don't vote, so one good read carries the slot
+        let reads = vec![vec![1234, 0], vec![1234, 0], vec![1234, 5678]];
+        let (teams, confidence) = vote_alliance(reads.into_iter());
+        assert_eq!(teams, vec![1234, 5678]);
+        assert_eq!(confidence, 1.0);
+
+        // nobody read the slot at all
+        let reads = vec![vec![1234, 0], vec![1234, 0]];
+        let (teams, confidence) = vote_alliance(reads.into_iter());
+        assert_eq!(teams, vec![1234, 0]);
+        assert_eq!(confidence, 0.0);
+
+        assert_eq!(vote_alliance(std::iter::empty()), (Vec::new(), 0.0));
+    }
+
+    #[test]
+    fn vote_alliance_mixed_sizes() {
+        // a three-team alliance where one frame dropped the last line
+        let reads = vec![vec![1, 2, 3], vec![1, 2, 3], vec![1, 2]];
+        let (teams, confidence) = vote_alliance(reads.into_iter());
+        assert_eq!(teams, vec![1, 2, 3]);
+        assert!((confidence - 2.0 / 3.0).abs() < 1e-9);
+
+        // two and four team alliances in the same vote
+        let reads = vec![vec![1, 2], vec![1, 2, 3, 4], vec![1, 2], vec![1, 2, 3, 4], vec![1, 2, 3, 4]];
+        let (teams, _) = vote_alliance(reads.into_iter());
+        assert_eq!(teams, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn lineup_vote() {
+        let detections = [
+            detection("Qualification 12", DisplayLayout::Traditional, &[1234, 5678], &[9012, 3456]),
+            detection("Qualification 12", DisplayLayout::Traditional, &[1234, 5678], &[9012, 3456]),
+            detection("Qualification 1Z", DisplayLayout::Traditional, &[1234, 0], &[9012, 3456]),
+            // a stray remote read doesn't get to make the blue alliance empty
+            detection("Qualification 12", DisplayLayout::Remote, &[1234], &[]),
+        ];
+        let lineup = Lineup::vote(detections.iter()).unwrap();
+        assert_eq!(lineup.name, "Qualification 12");
+        assert_eq!(lineup.layout, DisplayLayout::Traditional);
+        assert_eq!(lineup.red_alliance, vec![1234, 5678]);
+        assert_eq!(lineup.blue_alliance, vec![9012, 3456]);
+        assert_eq!(lineup.confidence, 0.75);
+        assert_eq!(lineup.teams_string(), "1234 5678 vs 9012 3456");
+    }
+
+    #[test]
+    fn lineup_vote_remote() {
+        let detections = [
+            detection("Qualification 3", DisplayLayout::Remote, &[16000], &[]),
+            detection("Qualification 3", DisplayLayout::Remote, &[16000], &[]),
+        ];
+        let lineup = Lineup::vote(detections.iter()).unwrap();
+        assert_eq!(lineup.red_alliance, vec![16000]);
+        assert!(lineup.blue_alliance.is_empty());
+        assert_eq!(lineup.teams_string(), "16000");
+    }
+
+    #[test]
+    fn lineup_vote_skips_unresolved() {
+        let mut off_roster = detection("Qualification 12", DisplayLayout::Traditional, &[1234, 9999], &[9012, 3456]);
+        off_roster.display_info.unresolved_teams = vec![9999];
+        let detections = [
+            off_roster.clone(),
+            off_roster,
+            detection("Qualification 12", DisplayLayout::Traditional, &[1234, 5678], &[9012, 3456]),
+        ];
+        let lineup = Lineup::vote(detections.iter()).unwrap();
+        assert_eq!(lineup.red_alliance, vec![1234, 5678]);
+    }
+}
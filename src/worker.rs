@@ -1,7 +1,10 @@
 use std::collections::{BTreeMap, HashSet, VecDeque};
-use clipcrab_detect::{MatchDetection, MatchKey, qr::FTCEventsQR};
+use std::path::PathBuf;
 
-use crate::model::{Match, Segment, WithTime};
+use clipcrab_detect::{FrameClass, MatchDetection, MatchKey, ResultsScreen, locale::Locale, match_name};
+
+use crate::manifest::{Manifest, ManifestCeremony, ManifestClip, ManifestEvent, ManifestMatch};
+use crate::model::{Ceremony, Event, MatchAttempt, MatchId, Segment, WithTime, event_at, event_timeline};
 
 /*
 Basic flow:
@@ -28,13 +31,57 @@ Event struct:
 
 
 */
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// Length of a match from the start of auto to the end of teleop, including the transition.
+const MATCH_LENGTH_US: i64 = 158_000_000;
+/// How much video to keep after the last detect of an aborted run.
+const ABORTED_TAIL_US: i64 = 5_000_000;
+/// Fuzzy-parsed match names below this confidence are thrown away.
+const MIN_MATCH_NAME_CONFIDENCE: f64 = 0.75;
+/// How far past the end of a match we go looking for a results screen we missed.
+const LATE_RESULTS_WINDOW_US: i64 = 300_000_000;
+/// Frames closer together than this get decoded in one forward pass instead of seeking to each.
+/// Every seek decodes again from the previous keyframe, and streams put those a couple of seconds apart.
+const SEQUENTIAL_MAX_GAP_US: i64 = 2_000_000;
+/// Most frames in one sequential run, so runs still spread over the workers.
+const SEQUENTIAL_MAX_FRAMES: usize = 30;
+/// Length of the stretch of stream each worker takes in a keyframe scan.
+const KEYFRAME_CHUNK_US: i64 = 600_000_000;
+/// Times an event name has to be read before it becomes an event of its own, so one-off misreads don't.
+const NEW_EVENT_MIN_SIGHTINGS: usize = 5;
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct ClipMatchRequest {
-    pub key: MatchKey,
+    pub id: MatchId,
+    /// Subdirectory of the output directory, one per event
+    pub dir: Option<String>,
+    /// Title written into the clip's metadata
+    pub title: String,
+    /// 1-based attempt number
+    pub attempt: usize,
+    /// Whether this is an aborted attempt rather than the official run
+    pub aborted: bool,
     pub match_segment: Segment,
     pub result_segment: Option<Segment>,
 }
 
+impl ClipMatchRequest {
+    pub fn file_name(&self) -> String {
+        if self.aborted {
+            format!("{}-aborted{}.mkv", self.id.compact(), self.attempt)
+        } else {
+            format!("{}.mkv", self.id.compact())
+        }
+    }
+
+    /// Path relative to the output directory.
+    pub fn path(&self) -> PathBuf {
+        match &self.dir {
+            Some(dir) => PathBuf::from(dir).join(self.file_name()),
+            None => PathBuf::from(self.file_name()),
+        }
+    }
+}
+
 fn pprint_ts(ts: i64) -> String {
     format!("{:02}:{:02}:{:02}.{:06}", 
         ts / (3600 * 1_000_000),
@@ -44,11 +91,38 @@ fn pprint_ts(ts: i64) -> String {
     )
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// Clip of an alliance selection or awards ceremony.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct ClipCeremonyRequest {
+    pub ceremony: Ceremony,
+    /// Subdirectory of the output directory, one per event
+    pub dir: Option<String>,
+    /// Title written into the clip's metadata
+    pub title: String,
+    pub segment: Segment,
+}
+
+impl ClipCeremonyRequest {
+    /// Path relative to the output directory.
+    pub fn path(&self) -> PathBuf {
+        let file_name = format!("{}.mkv", self.ceremony.stem());
+        match &self.dir {
+            Some(dir) => PathBuf::from(dir).join(file_name),
+            None => PathBuf::from(file_name),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Task {
     /// Analyze a frame at the microsecond timestamp.
     AnalyzeFrame(i64),
+    /// Analyze frames at the sorted microsecond timestamps, decoding forward from the first.
+    AnalyzeRun(Vec<i64>),
+    /// Analyze every keyframe in the segment, at whatever times they fall.
+    AnalyzeKeyframes(Segment),
     ClipMatch(ClipMatchRequest),
+    ClipCeremony(ClipCeremonyRequest),
     Done,
 }
 
@@ -61,13 +135,34 @@ impl core::fmt::Display for Task {
                  .field(&pprint_ts(ts))
                  .finish()
             }
+            Task::AnalyzeRun(timestamps) => {
+                f.debug_struct("AnalyzeRun")
+                 .field("start", &timestamps.first().map(|ts| pprint_ts(*ts)))
+                 .field("end", &timestamps.last().map(|ts| pprint_ts(*ts)))
+                 .field("frames", &timestamps.len())
+                 .finish()
+            }
+            Task::AnalyzeKeyframes(segment) => {
+                f.debug_tuple("AnalyzeKeyframes")
+                 .field(&(pprint_ts(segment.start), segment.duration()))
+                 .finish()
+            }
             Task::ClipMatch(clip_match_request) => {
                 f.debug_struct("ClipMatch")
-                .field("key", &clip_match_request.key)
+                .field("id", &clip_match_request.id)
+                .field("attempt", &clip_match_request.attempt)
+                .field("aborted", &clip_match_request.aborted)
                 .field("segment", &(pprint_ts(clip_match_request.match_segment.start), clip_match_request.match_segment.duration()))
                 .field("result_screen", &clip_match_request.result_segment.map(|s| (pprint_ts(s.start), s.duration())))
                 .finish()
             }
+            Task::ClipCeremony(request) => {
+                f.debug_struct("ClipCeremony")
+                .field("ceremony", &request.ceremony)
+                .field("dir", &request.dir)
+                .field("segment", &(pprint_ts(request.segment.start), request.segment.duration()))
+                .finish()
+            }
             Task::Done => {
                 f.debug_struct("Done").finish()
             }
@@ -77,10 +172,11 @@ impl core::fmt::Display for Task {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TaskResult {
-    None,
     Error(String),
-    MatchDetection(i64, MatchDetection),
-    MatchResultQR(i64, FTCEventsQR),
+    /// What the frame at the given time shows
+    Frame(i64, FrameClass),
+    /// What the frames of an [`Task::AnalyzeRun`] or [`Task::AnalyzeKeyframes`] show
+    Frames(Vec<(i64, FrameClass)>),
     ClipDone,
 }
 
@@ -94,42 +190,103 @@ pub struct TaskSubmission {
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 enum ProjectState {
     InitialScan,
+    /// Looking harder after matches we found no results screen for
+    ResultsSearch,
     ClipMatches,
     Done,
 }
 
 pub struct OfflineEventProject {
     state: ProjectState,
-    //duration_us: i64,
+    start: i64,
+    duration_us: i64,
     next_tasks: VecDeque<Task>,
     in_flight: HashSet<Task>,
 
-    matches: BTreeMap<MatchKey, Match>,
-
+    /// Events in the stream, in order of first appearance
+    events: Vec<Event>,
+    /// Event names read fewer than [`NEW_EVENT_MIN_SIGHTINGS`] times, with how many times they were read
+    pending_events: Vec<(String, usize)>,
+    /// Detects whose event name couldn't be read, handed to events once the scan is done
+    unassigned_detections: Vec<WithTime<(MatchKey, MatchDetection)>>,
+    /// Results screens seen, tied to matches once the scan is done
+    results_screens: Vec<WithTime<ResultsScreen>>,
+    /// Alliance selection and awards frames, handed to events once the scan is done
+    ceremony_frames: Vec<WithTime<Ceremony>>,
+    /// If not empty, only these matches get clipped
+    only: Vec<MatchKey>,
+    /// Start clips at the match preview instead of the match start
+    intros: bool,
+    /// Language of the scoring software
+    locale: Locale,
+    /// OCR'd match names we couldn't parse, with how many frames they showed up in
+    rejected_names: BTreeMap<String, usize>,
+    manifest: Manifest,
 }
 
 impl OfflineEventProject {
     pub fn new(start: i64, duration_us: i64) -> Self {
         Self {
             state: ProjectState::InitialScan,
-            //duration_us,
-            next_tasks: (start..duration_us)
+            start,
+            duration_us,
+            next_tasks: analyze_tasks(
                 // every 1s screw it 
                 // efficient? no. but it's gonna take weeks to figure out the logic to do this more efficiently
                 // and scaling up compute is easy
-                .step_by(1_000_000)
-                .map(Task::AnalyzeFrame)
-                .collect(),
+                (start..duration_us).step_by(1_000_000)
+            ).into(),
             in_flight: HashSet::new(),
-            matches: BTreeMap::new(),
+            events: Vec::new(),
+            pending_events: Vec::new(),
+            unassigned_detections: Vec::new(),
+            results_screens: Vec::new(),
+            ceremony_frames: Vec::new(),
+            only: Vec::new(),
+            intros: false,
+            locale: Locale::English,
+            rejected_names: BTreeMap::new(),
+            manifest: Manifest::default(),
         }
     }
 
+    /// Only clip the matches with these keys. An empty list clips everything.
+    pub fn with_only(mut self, keys: Vec<MatchKey>) -> Self {
+        self.only = keys;
+        self
+    }
+
+    /// Do the initial scan on keyframes only, rather than a frame every second.
+    ///
+    /// Much faster on long streams, but frames only come as often as the encoder put in keyframes,
+    /// so streams with long keyframe intervals can miss short screens.
+    pub fn with_keyframe_scan(mut self, keyframe_scan: bool) -> Self {
+        if keyframe_scan && self.state == ProjectState::InitialScan {
+            self.next_tasks = (self.start..self.duration_us)
+                .step_by(KEYFRAME_CHUNK_US as usize)
+                .map(|start| Task::AnalyzeKeyframes(Segment::from_start_end(start, (start + KEYFRAME_CHUNK_US).min(self.duration_us))))
+                .collect();
+        }
+        self
+    }
+
+    /// Start clips at the first preview (team intro) frame before each run, where there is one.
+    pub fn with_intros(mut self, intros: bool) -> Self {
+        self.intros = intros;
+        self
+    }
+
+    /// Parses match names from localized scoring software.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
     /// Pumps the state machine to attempt to produce output.
     pub fn next(&mut self) -> Option<Task> {
 
         if let Some(next) = self.next_tasks.pop_front() {
-            self.in_flight.insert(next);
+            self.in_flight.insert(next.clone());
             return Some(next);
         }
         if !self.in_flight.is_empty() {
@@ -139,6 +296,13 @@ impl OfflineEventProject {
         let state = self.state;
         match state {
             ProjectState::InitialScan => {
+                self.collate();
+                self.queue_results_search();
+                self.state = ProjectState::ResultsSearch;
+            }
+            ProjectState::ResultsSearch => {
+                self.collate();
+                self.plan_clips();
                 self.state = ProjectState::ClipMatches;
             }
             ProjectState::ClipMatches => {
@@ -147,13 +311,240 @@ impl OfflineEventProject {
             ProjectState::Done => {}
         }
         if let Some(next) = self.next_tasks.pop_front() {
-            self.in_flight.insert(next);
+            self.in_flight.insert(next.clone());
             Some(next)
         } else {
             None
         }
     }
 
+    /// Parses an OCR'd match name, keeping track of the ones we throw away.
+    fn parse_match_name(&mut self, name: &str) -> Option<MatchKey> {
+        match clipcrab_detect::match_name::parse_localized(name, self.locale) {
+            Ok(parsed) if parsed.confidence >= MIN_MATCH_NAME_CONFIDENCE => Some(parsed.key),
+            result => {
+                let count = self.rejected_names.entry(name.to_string()).or_insert(0);
+                if *count == 0 {
+                    match result {
+                        Ok(parsed) => tracing::warn!("Rejected match name {name:?}: parsed as `{}` with low confidence {:.2}", parsed.key, parsed.confidence),
+                        Err(e) => tracing::warn!("Rejected match name {name:?}: {e}"),
+                    }
+                }
+                *count += 1;
+                None
+            }
+        }
+    }
+
+    /// Finds the event with this (OCR'd) name, snapping to the nearest one we've already seen if it's close enough.
+    ///
+    /// Divisions have to match exactly, since "Field 1" and "Field 2" are one edit apart. Only the rest of the name is fuzzy.
+    /// Names that match no event only make a new one after [`NEW_EVENT_MIN_SIGHTINGS`] reads; until then this returns `None`,
+    /// and the detect goes to whichever event was on screen at the time.
+    fn event_index(&mut self, raw: &str) -> Option<usize> {
+        let name = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return None;
+        }
+        let (division, rest) = split_division(&name);
+        // edit distance to `known`, if it's close enough to be the same event
+        let distance = |known: &str| {
+            let (known_division, known_rest) = split_division(known);
+            let distance = clipcrab_detect::utils::edit_distance(&known_rest, &rest);
+            (known_division == division && distance <= (known_rest.chars().count() / 8).max(2)).then_some(distance)
+        };
+        let known = self.events
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| Some((e.name.as_deref().and_then(distance)?, i)))
+            .min()
+            .map(|(_, i)| i);
+        if known.is_some() {
+            return known;
+        }
+
+        let pending = match self.pending_events.iter().position(|(pending, _)| distance(pending).is_some()) {
+            Some(i) => i,
+            None => {
+                self.pending_events.push((name, 0));
+                self.pending_events.len() - 1
+            }
+        };
+        self.pending_events[pending].1 += 1;
+        if self.pending_events[pending].1 < NEW_EVENT_MIN_SIGHTINGS {
+            return None;
+        }
+        let (name, _) = self.pending_events.remove(pending);
+        tracing::info!("New event: {name:?}");
+        self.events.push(Event::new(Some(name)));
+        Some(self.events.len() - 1)
+    }
+
+    fn add_detection(&mut self, time_us: i64, match_detection: MatchDetection) {
+        let Some(key) = self.parse_match_name(&match_detection.name) else {
+            return;
+        };
+        match match_detection.event_name.as_deref().and_then(|name| self.event_index(name)) {
+            Some(i) => self.events[i].add_detection(key, WithTime::new(time_us, match_detection)),
+            None => self.unassigned_detections.push(WithTime::new(time_us, (key, match_detection))),
+        }
+    }
+
+    /// Hands detects whose event name we couldn't read to whichever event was on screen at the time.
+    fn assign_detections(&mut self) {
+        let timeline = event_timeline(&self.events);
+        for det in std::mem::take(&mut self.unassigned_detections) {
+            let i = event_at(&timeline, det.frame_ts_us).unwrap_or(0);
+            let (key, detection) = det.value;
+            self.events[i].add_detection(key, WithTime::new(det.frame_ts_us, detection));
+        }
+    }
+
+    /// Ties results screens to events and matches, learning event codes along the way.
+    ///
+    /// The QR code gives us the event code but the overlay only shows the event name,
+    /// so until we know an event's code we go by whichever event was on screen around the results screen.
+    /// Results screens read without a QR code never tell us a code, so they always go by the timeline.
+    fn link_results_screens(&mut self) {
+        let timeline = event_timeline(&self.events);
+        let mut screens = std::mem::take(&mut self.results_screens);
+        screens.sort();
+        for screen in screens {
+            let by_code = screen.event_code.as_ref().and_then(|code| self.events.iter().position(|e| e.code.as_ref() == Some(code)));
+            let i = match by_code.or_else(|| event_at(&timeline, screen.frame_ts_us)) {
+                Some(i) if self.events[i].code.as_ref().zip(screen.event_code.as_ref()).is_none_or(|(a, b)| a == b) => i,
+                _ => {
+                    // an event we never saw the overlay for
+                    self.events.push(Event::new(None));
+                    self.events.len() - 1
+                }
+            };
+            let event = &mut self.events[i];
+            if event.code.is_none() && let Some(code) = &screen.event_code {
+                event.set_code(code);
+            }
+            event.add_results_screen(screen.key, screen.frame_ts_us, screen.scores);
+        }
+    }
+
+    /// Files everything scanned so far under its event and match.
+    fn collate(&mut self) {
+        let anything_scanned = !(self.unassigned_detections.is_empty() && self.results_screens.is_empty() && self.ceremony_frames.is_empty());
+        if self.events.is_empty() && anything_scanned {
+            self.events.push(Event::new(None));
+        }
+        self.assign_detections();
+        self.link_results_screens();
+
+        let timeline = event_timeline(&self.events);
+        for frame in std::mem::take(&mut self.ceremony_frames) {
+            // like detects, with no overlay seen at all it's the first event
+            let i = event_at(&timeline, frame.frame_ts_us).unwrap_or(0);
+            self.events[i].add_ceremony(frame.value, frame.frame_ts_us);
+        }
+    }
+
+    /// Queues frames in between the 1s scan after every match with no results screen yet,
+    /// up to the next match or [`LATE_RESULTS_WINDOW_US`], whichever comes first.
+    fn queue_results_search(&mut self) {
+        let mut first_seens = self.events
+            .iter()
+            .flat_map(|e| e.matches.values())
+            .filter_map(|m| m.first_seen())
+            .collect::<Vec<i64>>();
+        first_seens.sort();
+
+        for m in self.events.iter_mut().flat_map(|e| e.matches.values_mut()) {
+            if !self.only.is_empty() && !self.only.contains(&m.id.key) {
+                continue;
+            }
+            m.calc_attempts();
+            if m.calc_result_screen().is_some() {
+                continue;
+            }
+            let Some(attempt) = m.official_attempt() else {
+                continue;
+            };
+            let end = (attempt.start() + MATCH_LENGTH_US + attempt.paused_us()).max(m.last_seen().unwrap_or(0));
+            let next_match = first_seens.iter().copied().find(|t| *t > end).unwrap_or(i64::MAX);
+            let search_end = (end + LATE_RESULTS_WINDOW_US).min(next_match).min(self.duration_us);
+            tracing::info!("No results screen for `{}`, searching {}..{}", m.id, pprint_ts(end), pprint_ts(search_end));
+            self.next_tasks.extend(analyze_tasks(
+                // offset by half a second from the initial scan
+                ((end + 500_000)..search_end).step_by(1_000_000)
+            ));
+        }
+    }
+
+    /// Works out the attempts for every match and queues up their clips.
+    fn plan_clips(&mut self) {
+        if !self.rejected_names.is_empty() {
+            tracing::info!("Rejected match names (name -> frames): {:?}", self.rejected_names);
+        }
+        let timeline = event_timeline(&self.events);
+
+        for (event_idx, event) in self.events.iter_mut().enumerate() {
+            let dir = event.dir_name();
+            let mut manifest_event = ManifestEvent {
+                name: event.name.clone(),
+                code: event.code.clone(),
+                dir: dir.clone(),
+                segments: timeline.iter().filter(|(_, e)| *e == event_idx).map(|(s, _)| *s).collect(),
+                matches: Vec::new(),
+                ceremonies: Vec::new(),
+            };
+
+            for &ceremony in event.ceremonies.keys() {
+                let Some(segment) = event.calc_ceremony(ceremony) else {
+                    continue;
+                };
+                let title = match event.name.as_ref().or(event.code.as_ref()) {
+                    Some(name) => format!("{name} {ceremony}"),
+                    None => ceremony.to_string(),
+                };
+                let request = ClipCeremonyRequest { ceremony, dir: dir.clone(), title, segment };
+                manifest_event.ceremonies.push(ManifestCeremony::new(&request));
+                self.next_tasks.push_back(Task::ClipCeremony(request));
+            }
+
+            for m in event.matches.values_mut() {
+                if !self.only.is_empty() && !self.only.contains(&m.id.key) {
+                    continue;
+                }
+                m.calc_attempts();
+                m.calc_lineup();
+                m.calc_scores();
+                let result_segment = m.calc_result_screen();
+                let end_hold = m.calc_end_hold();
+                let mut entry = ManifestMatch::new(m.id.clone(), m.lineup.clone(), m.scores);
+                let title = match &m.lineup {
+                    Some(lineup) => format!("{} ({})", m.id, lineup.teams_string()),
+                    None => m.id.to_string(),
+                };
+
+                for (i, attempt) in m.attempts.iter().enumerate() {
+                    let timing = ClipTiming { attempt: i + 1, run: attempt, result_segment, end_hold, intro: self.intros };
+                    let request = clip_request(m.id.clone(), dir.clone(), &title, &timing);
+                    entry.clips.push(ManifestClip::new(&request, attempt));
+                    self.next_tasks.push_back(Task::ClipMatch(request));
+                }
+                if m.official_attempt().is_none() {
+                    tracing::warn!("No official attempt found for `{}`, not clipping it", m.id);
+                }
+                manifest_event.matches.push(entry);
+            }
+            self.manifest.events.push(manifest_event);
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.state == ProjectState::Done && self.next_tasks.is_empty() && self.in_flight.is_empty()
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
     pub fn waiting_on_result(&self) -> bool {
         !self.in_flight.is_empty()
     }
@@ -166,25 +557,12 @@ impl OfflineEventProject {
         self.in_flight.remove(&submission.task);
         let state = self.state;
         match state {
-            ProjectState::InitialScan => {
+            ProjectState::InitialScan | ProjectState::ResultsSearch => {
                 match submission.result {
-                    TaskResult::MatchDetection(time_us, match_detection) => {
-                        if let Ok(key) = match_detection.name.parse::<MatchKey>() {
-                            if !self.matches.contains_key(&key) {
-                                self.matches.insert(key, Match::new(key));
-                            }
-                            if let Some(ent) = self.matches.get_mut(&key) {
-                                ent.add_detection(WithTime::new(time_us, match_detection));
-                            }
-                        }
-                    }
-                    TaskResult::MatchResultQR(time_us, qr) => {
-                        let key = qr.key;
-                        if !self.matches.contains_key(&key) {
-                            self.matches.insert(key, Match::new(key));
-                        }
-                        if let Some(ent) = self.matches.get_mut(&key) {
-                            ent.add_results_screen(time_us);
+                    TaskResult::Frame(time_us, class) => self.add_frame(time_us, class),
+                    TaskResult::Frames(frames) => {
+                        for (time_us, class) in frames {
+                            self.add_frame(time_us, class);
                         }
                     }
                     TaskResult::Error(e) => {
@@ -202,9 +580,190 @@ impl OfflineEventProject {
         }
     }
 
+    fn add_frame(&mut self, time_us: i64, class: FrameClass) {
+        match class {
+            FrameClass::InMatch(match_detection) | FrameClass::Preview(match_detection) => {
+                self.add_detection(time_us, match_detection);
+            }
+            FrameClass::Results(screen) => {
+                self.results_screens.push(WithTime::new(time_us, screen));
+            }
+            class => match Ceremony::from_frame(&class) {
+                Some(ceremony) => self.ceremony_frames.push(WithTime::new(time_us, ceremony)),
+                None => tracing::trace!("Frame at {} is {class:?}", pprint_ts(time_us)),
+            },
+        }
+    }
+
+}
+
+/// Groups timestamps close enough together into sequential runs, and seeks to the rest one at a time.
+fn analyze_tasks(timestamps: impl IntoIterator<Item = i64>) -> Vec<Task> {
+    let mut timestamps = timestamps.into_iter().collect::<Vec<i64>>();
+    timestamps.sort();
+
+    let mut tasks = Vec::new();
+    let mut run: Vec<i64> = Vec::new();
+    let mut flush = |run: &mut Vec<i64>| match run.len() {
+        0 => {}
+        1 => tasks.push(Task::AnalyzeFrame(run.pop().unwrap())),
+        _ => tasks.push(Task::AnalyzeRun(std::mem::take(run))),
+    };
+    for ts in timestamps {
+        let close = run.last().is_some_and(|last| ts - last <= SEQUENTIAL_MAX_GAP_US);
+        if !close || run.len() >= SEQUENTIAL_MAX_FRAMES {
+            flush(&mut run);
+        }
+        run.push(ts);
+    }
+    flush(&mut run);
+    tasks
+}
+
+/// Splits an event name into its division (lowercase, see [`match_name::division`]) and the rest of the name, lowercase.
+fn split_division(name: &str) -> (Option<String>, String) {
+    let lower = name.to_lowercase();
+    let Some(division) = match_name::division(&lower).map(|d| d.to_lowercase()) else {
+        return (None, lower);
+    };
+    let rest = lower.replacen(&division, "", 1).split_whitespace().collect::<Vec<_>>().join(" ");
+    (Some(division), rest)
+}
+
+/// Where one attempt's clip starts and ends.
+struct ClipTiming<'a> {
+    /// 1-based attempt number
+    attempt: usize,
+    run: &'a MatchAttempt,
+    result_segment: Option<Segment>,
+    end_hold: Option<Segment>,
+    /// Start at the team intro, if there is one
+    intro: bool,
+}
+
+fn clip_request(id: MatchId, dir: Option<String>, title: &str, timing: &ClipTiming) -> ClipMatchRequest {
+    let &ClipTiming { attempt, run, result_segment, end_hold, intro } = timing;
+    let clip_start = run.intro_start.filter(|_| intro).unwrap_or(run.start());
+    if run.aborted {
+        return ClipMatchRequest {
+            id,
+            dir,
+            title: format!("{title} - aborted attempt {attempt}"),
+            attempt,
+            aborted: true,
+            match_segment: Segment::from_start_end(clip_start, run.fit.last_seen + ABORTED_TAIL_US),
+            result_segment: None,
+        };
+    }
+
+    let mut match_segment = Segment::from_start_end(clip_start, run.start() + MATCH_LENGTH_US + run.paused_us());
+    if let Some(result_segment) = result_segment {
+        // the result segment picks up a bit before the results screen, don't show that twice
+        match_segment.end = match_segment.end.min(result_segment.start);
+    } else if let Some(end_hold) = end_hold {
+        // no results screen, so run until the scoring display stops showing 0:00
+        match_segment.end = match_segment.end.max(end_hold.end);
+    }
+    ClipMatchRequest {
+        id,
+        dir,
+        title: title.to_string(),
+        attempt,
+        aborted: false,
+        match_segment,
+        result_segment,
+    }
 }
 
 pub trait WorkerConnection {
     fn next_job(&mut self) -> anyhow::Result<Task>;
     fn submit(&mut self, submission: TaskSubmission) -> anyhow::Result<()>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `name` enough times to make it an event.
+    fn confirm_event(project: &mut OfflineEventProject, name: &str) -> usize {
+        (0..NEW_EVENT_MIN_SIGHTINGS).filter_map(|_| project.event_index(name)).last().unwrap()
+    }
+
+    #[test]
+    fn event_index_keeps_divisions_apart() {
+        let mut project = OfflineEventProject::new(0, 0);
+        let field_1 = confirm_event(&mut project, "Championship Field 1");
+        let field_2 = confirm_event(&mut project, "Championship Field 2");
+        assert_ne!(field_1, field_2);
+        assert_eq!(project.event_index("Championship  Field 1"), Some(field_1));
+        assert_eq!(project.event_index("Champlonship Field 2"), Some(field_2));
+
+        let edison = confirm_event(&mut project, "FIRST Championship - Edison Division");
+        let ochoa = confirm_event(&mut project, "FIRST Championship - Ochoa Division");
+        assert_ne!(edison, ochoa);
+        assert_eq!(project.event_index("FIRST Champi0nship - Edison Division"), Some(edison));
+        assert_eq!(project.events.len(), 4);
+    }
+
+    #[test]
+    fn event_index_fuzzy_without_division() {
+        let mut project = OfflineEventProject::new(0, 0);
+        let meet = confirm_event(&mut project, "Bay Area League Meet 3");
+        assert_eq!(project.event_index("Bay Area Leaque Meet 3"), Some(meet));
+        assert_eq!(confirm_event(&mut project, "Central Valley Qualifier"), 1);
+        assert_eq!(project.event_index("   "), None);
+    }
+
+    #[test]
+    fn event_index_snaps_to_nearest() {
+        let mut project = OfflineEventProject::new(0, 0);
+        let meet_3 = confirm_event(&mut project, "Bay Area League Meet 3");
+        let meet_4 = confirm_event(&mut project, "Bay Area League Meet 4");
+        assert_eq!(project.event_index("Bay Area Leaque Meet 4"), Some(meet_4));
+        assert_eq!(project.event_index("Bay Area Leaque Meet 3"), Some(meet_3));
+    }
+
+    #[test]
+    fn event_index_ignores_misreads() {
+        let mut project = OfflineEventProject::new(0, 0);
+        let meet = confirm_event(&mut project, "Bay Area League Meet 3");
+        // too garbled to snap, but not read often enough to be an event either
+        for misread in ["Bav Ar3a Lcaque Mcct 3", "Bay Arca Leaguc Meel 8 ", "Bay", "Area League Meet 3 Bay Area"] {
+            for _ in 1..NEW_EVENT_MIN_SIGHTINGS {
+                assert_eq!(project.event_index(misread), None, "{misread}");
+            }
+        }
+        assert_eq!(project.events.len(), 1);
+        assert_eq!(project.event_index("Bay Area League Meet 3"), Some(meet));
+    }
+
+    #[test]
+    fn event_index_counts_chars() {
+        // 16 chars but 31 bytes: going by bytes would let three edits through
+        let mut project = OfflineEventProject::new(0, 0);
+        let first = confirm_event(&mut project, "Соревнования Лиг");
+        assert_ne!(confirm_event(&mut project, "Соревнования Ажх"), first);
+    }
+
+    #[test]
+    fn collate_ceremonies_without_overlay() {
+        // a stream of just the awards, with no scoring display to name the event
+        let mut project = OfflineEventProject::new(0, 0);
+        for t in 0..30 {
+            project.add_frame(t * 1_000_000, FrameClass::Awards);
+        }
+        project.add_frame(40_000_000, FrameClass::AllianceSelection);
+        project.collate();
+
+        assert_eq!(project.events.len(), 1);
+        assert_eq!(project.events[0].ceremonies[&Ceremony::Awards].len(), 30);
+        assert_eq!(project.events[0].ceremonies[&Ceremony::AllianceSelection].len(), 1);
+    }
+
+    #[test]
+    fn split_division_names() {
+        assert_eq!(split_division("Championship Field 1"), (Some("field 1".to_string()), "championship".to_string()));
+        assert_eq!(split_division("FIRST Championship - Edison Division"), (Some("edison".to_string()), "first championship - division".to_string()));
+        assert_eq!(split_division("League Meet"), (None, "league meet".to_string()));
+    }
+}